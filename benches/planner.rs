@@ -0,0 +1,99 @@
+//! A small, dependency-free benchmark of `Planner::plan`: `cargo bench --bench planner`.
+//!
+//! Each domain has `n` boolean facts with a "set" and "clear" action per fact, and a goal of setting
+//! all of them, so the search expands every subset of facts before reaching the goal.
+//!
+//! The "successor" rows time what the search does per successor, cloning a state, setting one fact
+//! and hashing it, for `WorldState` and for the plain `Vec<Value>` it used to be stored as.
+
+use std::hash::{BuildHasher, Hash, Hasher, RandomState};
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use dogsoap::{Action, Condition, Cost, Effect, FactId, Goal, Planner, Value, WorldState};
+
+fn domain(num_facts: u16, extra_facts: u16) -> (Planner, WorldState, Goal) {
+	let mut planner = Planner::new();
+	let mut goal = Goal::new("All set");
+	for i in 0..num_facts {
+		let fact = FactId(i);
+		let mut set = Action::new(format!("Set {i}"), Cost(1));
+		set.push_precondition(Condition::Eq(fact, Value::FALSE))
+			.push_effect(Effect::Set(fact, Value::TRUE));
+		let mut clear = Action::new(format!("Clear {i}"), Cost(1));
+		clear.push_effect(Effect::Set(fact, Value::FALSE));
+		planner.push_action(set);
+		planner.push_action(clear);
+		goal.push_condition(Condition::Eq(fact, Value::TRUE));
+	}
+	let start = WorldState::new(usize::from(num_facts + extra_facts));
+	(planner, start, goal)
+}
+
+fn bench(name: &str, num_facts: u16, extra_facts: u16) {
+	let (planner, start, goal) = domain(num_facts, extra_facts);
+	let mut iterations = 0u32;
+	let began = Instant::now();
+	while began.elapsed() < Duration::from_secs(2) {
+		let plan = planner.plan(black_box(&start), black_box(&goal));
+		assert_eq!(
+			plan.expect("no plan found").actions.len(),
+			usize::from(num_facts)
+		);
+		iterations += 1;
+	}
+	let per_plan = began.elapsed() / iterations;
+	println!("{name:<32} {per_plan:>12.2?} per plan ({iterations} iterations)");
+}
+
+fn time(name: &str, mut run: impl FnMut() -> u64) {
+	let mut iterations = 0u32;
+	let began = Instant::now();
+	while began.elapsed() < Duration::from_secs(1) {
+		for _ in 0..1000 {
+			black_box(run());
+		}
+		iterations += 1000;
+	}
+	let per_run = began.elapsed() / iterations;
+	println!("{name:<32} {per_run:>12.2?} per successor ({iterations} iterations)");
+}
+
+/// The representation `WorldState` had before it was packed into slots.
+#[derive(Clone)]
+struct VecState(Vec<Value>);
+
+impl Hash for VecState {
+	fn hash<H: Hasher>(&self, state: &mut H) {
+		state.write_usize(self.0.len());
+		for value in &self.0 {
+			value.hash(state);
+		}
+	}
+}
+
+fn bench_successor(num_facts: u16) {
+	let hasher = RandomState::new();
+	let fact = FactId(num_facts / 2);
+	let packed = WorldState::new(usize::from(num_facts));
+	time(&format!("successor, {num_facts} facts"), || {
+		let mut next = black_box(&packed).clone();
+		next.set(fact, Value::TRUE);
+		hasher.hash_one(&next)
+	});
+	let unpacked = VecState(vec![Value::FALSE; usize::from(num_facts)]);
+	time(&format!("successor, {num_facts} facts, Vec"), || {
+		let mut next = black_box(&unpacked).clone();
+		next.0[usize::from(fact.0)] = Value::TRUE;
+		hasher.hash_one(&next)
+	});
+}
+
+fn main() {
+	bench_successor(10);
+	bench_successor(64);
+	bench("8 facts", 8, 0);
+	bench("10 facts", 10, 0);
+	bench("10 facts, 6 untouched", 10, 6);
+	bench("10 facts, 54 untouched", 10, 54);
+}
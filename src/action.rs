@@ -1,6 +1,24 @@
 use crate::condition::Condition;
 use crate::cost::Cost;
 use crate::effect::Effect;
+use crate::executor::{Executor, ExecutorArg};
+use crate::fact::{FactId, FactRemap};
+
+/// Identifies an action in a `Planner`. Ids are never reused, so a `Plan` keeps referring to the
+/// same actions (or to none) after others are added or removed.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ActionId(pub u32);
+
+impl ActionId {
+	pub(crate) fn index(self) -> usize {
+		usize::try_from(self.0).unwrap_or(usize::MAX)
+	}
+
+	pub(crate) fn from_index(index: usize) -> Self {
+		Self(u32::try_from(index).unwrap_or(u32::MAX))
+	}
+}
 
 #[derive(Clone, Debug)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
@@ -9,7 +27,11 @@ pub struct Action {
 	pub cost: Cost,
 	pub preconditions: Vec<Condition>,
 	pub effects: Vec<Effect>,
-	pub executors: Vec<(String, String)>,
+	pub executors: Vec<Executor>,
+	/// Categories such as `movement` or `combat`, for `Planner::tag_mask`, `CostModifier` and
+	/// `Goal::allowed_tags`.
+	#[cfg_attr(feature = "serde", serde(default))]
+	pub tags: Vec<String>,
 }
 
 impl Action {
@@ -20,9 +42,20 @@ impl Action {
 			preconditions: Vec::new(),
 			effects: Vec::new(),
 			executors: Vec::new(),
+			tags: Vec::new(),
 		}
 	}
 
+	pub fn push_tag(&mut self, tag: impl Into<String>) -> &mut Self {
+		self.tags.push(tag.into());
+		self
+	}
+
+	#[must_use]
+	pub fn has_tag(&self, tag: &str) -> bool {
+		self.tags.iter().any(|own| own == tag)
+	}
+
 	pub fn push_precondition(&mut self, precondition: Condition) -> &mut Self {
 		self.preconditions.push(precondition);
 		self
@@ -33,16 +66,58 @@ impl Action {
 		self
 	}
 
+	/// A copy of this action with every `FactId` passed through `remap`.
+	#[must_use]
+	pub fn remapped(&self, remap: &FactRemap) -> Self {
+		Self {
+			name: self.name.clone(),
+			cost: self.cost,
+			preconditions: self
+				.preconditions
+				.iter()
+				.map(|condition| condition.remapped(remap))
+				.collect(),
+			effects: self
+				.effects
+				.iter()
+				.map(|effect| effect.remapped(remap))
+				.collect(),
+			executors: self
+				.executors
+				.iter()
+				.map(|executor| executor.remapped(remap))
+				.collect(),
+			tags: self.tags.clone(),
+		}
+	}
+
+	/// Every fact referenced by the preconditions, effects and executor arguments, possibly with repeats.
+	pub fn referenced_facts(&self) -> impl Iterator<Item = FactId> + '_ {
+		let executor_facts = self.executors.iter().flat_map(|executor| {
+			executor.args.iter().filter_map(|arg| match arg {
+				ExecutorArg::Fact(fact) => Some(*fact),
+				_ => None,
+			})
+		});
+		self.preconditions
+			.iter()
+			.flat_map(Condition::referenced_facts)
+			.chain(self.effects.iter().flat_map(Effect::referenced_facts))
+			.chain(executor_facts)
+	}
+
+	/// Legacy `(kind, args)` form, see `Executor::from((String, String))`.
 	pub fn push_executor(
 		&mut self,
 		executor_0: impl Into<String>,
 		executor_1: impl Into<String>,
 	) -> &mut Self {
-		self.executors.push((executor_0.into(), executor_1.into()));
+		self.executors
+			.push(Executor::from((executor_0.into(), executor_1.into())));
 		self
 	}
 
-	pub fn push_executorv(&mut self, executor: impl Into<(String, String)>) -> &mut Self {
+	pub fn push_executorv(&mut self, executor: impl Into<Executor>) -> &mut Self {
 		self.executors.push(executor.into());
 		self
 	}
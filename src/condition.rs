@@ -1,4 +1,6 @@
-use crate::fact::FactId;
+use core::cmp::Ordering;
+
+use crate::fact::{FactId, FactRemap};
 use crate::value::Value;
 use crate::world::WorldState;
 
@@ -15,19 +17,98 @@ pub enum Condition {
 
 impl Condition {
 	#[must_use]
-	pub fn is_satisfied(&self, state: &WorldState) -> bool {
+	pub fn fact(&self) -> FactId {
+		match *self {
+			Condition::Eq(fact, _)
+			| Condition::Ne(fact, _)
+			| Condition::Gt(fact, _)
+			| Condition::Lt(fact, _)
+			| Condition::Ge(fact, _)
+			| Condition::Le(fact, _) => fact,
+		}
+	}
+
+	#[must_use]
+	pub fn value(&self) -> Value {
 		match *self {
-			Condition::Eq(fact, value) => state.get(fact).eq(&value, state),
-			Condition::Ne(fact, value) => state.get(fact).ne(&value, state),
-			Condition::Gt(fact, value) => state.get(fact).gt(&value, state),
-			Condition::Lt(fact, value) => state.get(fact).lt(&value, state),
-			Condition::Ge(fact, value) => state.get(fact).ge(&value, state),
-			Condition::Le(fact, value) => state.get(fact).le(&value, state),
+			Condition::Eq(_, value)
+			| Condition::Ne(_, value)
+			| Condition::Gt(_, value)
+			| Condition::Lt(_, value)
+			| Condition::Ge(_, value)
+			| Condition::Le(_, value) => value,
 		}
 	}
+
+	#[must_use]
+	pub fn remapped(&self, remap: &FactRemap) -> Condition {
+		let (fact, value) = (remap.get(self.fact()), self.value().remapped(remap));
+		match self {
+			Condition::Eq(..) => Condition::Eq(fact, value),
+			Condition::Ne(..) => Condition::Ne(fact, value),
+			Condition::Gt(..) => Condition::Gt(fact, value),
+			Condition::Lt(..) => Condition::Lt(fact, value),
+			Condition::Ge(..) => Condition::Ge(fact, value),
+			Condition::Le(..) => Condition::Le(fact, value),
+		}
+	}
+
+	/// Every fact this condition reads: its own, and the one its value refers to.
+	pub fn referenced_facts(&self) -> impl Iterator<Item = FactId> {
+		core::iter::once(self.fact()).chain(self.value().ref_fact())
+	}
+
+	/// The operator as written by `FactMap::parse_condition`, e.g. `">="`.
+	#[must_use]
+	pub fn operator(&self) -> &'static str {
+		match self {
+			Condition::Eq(..) => "==",
+			Condition::Ne(..) => "!=",
+			Condition::Gt(..) => ">",
+			Condition::Lt(..) => "<",
+			Condition::Ge(..) => ">=",
+			Condition::Le(..) => "<=",
+		}
+	}
+
+	/// Three-valued: `None` if the fact or the value is (or resolves to) `Value::Error`, or the fact is
+	/// outside `state`, because the comparison has no answer.
+	#[must_use]
+	pub fn evaluate(&self, state: &WorldState) -> Option<bool> {
+		let ordering = Value::Ref(self.fact()).compare(&self.value(), state)?;
+		Some(match self {
+			Condition::Eq(..) => ordering == Ordering::Equal,
+			Condition::Ne(..) => ordering != Ordering::Equal,
+			Condition::Gt(..) => ordering == Ordering::Greater,
+			Condition::Lt(..) => ordering == Ordering::Less,
+			Condition::Ge(..) => ordering != Ordering::Less,
+			Condition::Le(..) => ordering != Ordering::Greater,
+		})
+	}
+
+	/// `evaluate`, treating an unknown result as unsatisfied.
+	#[must_use]
+	pub fn is_satisfied(&self, state: &WorldState) -> bool {
+		self.evaluate(state) == Some(true)
+	}
 }
 
 #[must_use]
 pub fn conditions_met(conditions: &[Condition], state: &WorldState) -> bool {
 	conditions.iter().all(|cond| cond.is_satisfied(state))
 }
+
+/// Three-valued AND of `Condition::evaluate`: `Some(false)` if any condition is false, otherwise
+/// `None` if any is unknown, otherwise `Some(true)`.
+#[must_use]
+pub fn evaluate_conditions(conditions: &[Condition], state: &WorldState) -> Option<bool> {
+	let mut result = Some(true);
+	for condition in conditions {
+		match condition.evaluate(state) {
+			Some(false) => return Some(false),
+			Some(true) => {}
+			None => result = None,
+		}
+	}
+	result
+}
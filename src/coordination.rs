@@ -0,0 +1,220 @@
+use std::collections::HashMap;
+
+use crate::action::Action;
+use crate::condition::Condition;
+use crate::effect::Effect;
+use crate::errors::{PlannerError, ReservationError};
+use crate::fact::FactId;
+use crate::goal::Goal;
+use crate::options::PlanOptions;
+use crate::plan::Plan;
+use crate::planner::Planner;
+use crate::world::WorldState;
+
+/// Identifies an agent taking part in a `Coordinator`.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AgentId(pub u32);
+
+/// Lets several agents plan against one shared `WorldState` without fighting over resources.
+///
+/// Facts registered with `push_resource` (e.g. `axe_available`) can be reserved by one agent at a
+/// time. `Coordinator::plan` reserves the resources an agent's plan uses, and hides every action
+/// touching another agent's reservations while planning, so later agents plan around them.
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Coordinator {
+	resources: Vec<FactId>,
+	reservations: HashMap<FactId, AgentId>,
+	plans: HashMap<AgentId, Plan>,
+}
+
+/// Two executing plans touching the same fact, where at least one of them writes it.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Conflict {
+	pub fact: FactId,
+	/// The agent whose plan writes `fact`, and the index of the first step that does.
+	pub writer: (AgentId, usize),
+	/// The other agent, and the index of its first step reading or writing `fact`.
+	pub other: (AgentId, usize),
+}
+
+impl Coordinator {
+	#[must_use]
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Make `fact` reservable.
+	pub fn push_resource(&mut self, fact: FactId) -> &mut Self {
+		if !self.resources.contains(&fact) {
+			self.resources.push(fact);
+		}
+		self
+	}
+
+	#[must_use]
+	pub fn is_resource(&self, fact: FactId) -> bool {
+		self.resources.contains(&fact)
+	}
+
+	#[must_use]
+	pub fn reserved_by(&self, fact: FactId) -> Option<AgentId> {
+		self.reservations.get(&fact).copied()
+	}
+
+	/// Reserve `fact` for `agent`; reserving it again for the same agent does nothing.
+	///
+	/// # Errors
+	/// - `ReservationError::NotAResource`: if `fact` wasn't registered with `push_resource`
+	/// - `ReservationError::Reserved`: if another agent holds it
+	pub fn reserve(&mut self, agent: AgentId, fact: FactId) -> Result<(), ReservationError> {
+		if !self.is_resource(fact) {
+			return Err(ReservationError::NotAResource(fact));
+		}
+		match self.reserved_by(fact) {
+			Some(by) if by != agent => Err(ReservationError::Reserved { fact, by }),
+			_ => {
+				self.reservations.insert(fact, agent);
+				Ok(())
+			}
+		}
+	}
+
+	/// Release `fact` if `agent` holds it.
+	pub fn release(&mut self, agent: AgentId, fact: FactId) {
+		if self.reserved_by(fact) == Some(agent) {
+			self.reservations.remove(&fact);
+		}
+	}
+
+	/// Forget `agent`'s plan and release everything it holds, e.g. once the plan finished or failed.
+	pub fn finish(&mut self, agent: AgentId) {
+		self.plans.remove(&agent);
+		self.reservations.retain(|_, by| *by != agent);
+	}
+
+	/// The plan `agent` is executing, as recorded by `plan`.
+	#[must_use]
+	pub fn get_plan(&self, agent: AgentId) -> Option<&Plan> {
+		self.plans.get(&agent)
+	}
+
+	/// `Planner::plan_with` for `agent`, without the actions that touch resources reserved by other
+	/// agents. The agent's previous plan is replaced, and its reservations become the resources the
+	/// new plan touches.
+	///
+	/// # Errors
+	/// See `Planner::plan`. On error the agent keeps its previous plan and reservations.
+	pub fn plan(
+		&mut self,
+		planner: &Planner,
+		agent: AgentId,
+		start: &WorldState,
+		goal: &Goal,
+		options: &PlanOptions,
+	) -> Result<Plan, PlannerError> {
+		let mut options = options.clone();
+		options.mask = Some(
+			planner
+				.actions()
+				.filter(|(id, action)| {
+					options.allows(*id)
+						&& touched_facts(action)
+							.all(|fact| self.reserved_by(fact).is_none_or(|by| by == agent))
+				})
+				.map(|(id, _)| id)
+				.collect(),
+		);
+		let plan = planner.plan_with(start, goal, &options)?;
+
+		self.reservations.retain(|_, by| *by != agent);
+		for action in plan.actions.iter().filter_map(|id| planner.get_action(*id)) {
+			for fact in touched_facts(action) {
+				if self.is_resource(fact) {
+					self.reservations.insert(fact, agent);
+				}
+			}
+		}
+		self.plans.insert(agent, plan.clone());
+		Ok(plan)
+	}
+
+	/// Every pair of recorded plans that touch a common fact which at least one of them writes,
+	/// whether or not it is a resource. Plans made through `plan` never conflict over resources, but
+	/// may over other shared facts, or after `record_plan`.
+	#[must_use]
+	pub fn conflicts(&self, planner: &Planner) -> Vec<Conflict> {
+		let mut agents: Vec<(AgentId, Vec<(usize, &Action)>)> = self
+			.plans
+			.iter()
+			.map(|(agent, plan)| (*agent, plan_steps(planner, plan)))
+			.collect();
+		agents.sort_unstable_by_key(|(agent, _)| *agent);
+
+		let mut conflicts = Vec::new();
+		for (index, (a, steps_a)) in agents.iter().enumerate() {
+			for (b, steps_b) in &agents[index + 1..] {
+				// one conflict per fact, even if both plans write it
+				let mut pair: Vec<Conflict> =
+					plan_conflicts((*a, steps_a), (*b, steps_b)).collect();
+				for conflict in plan_conflicts((*b, steps_b), (*a, steps_a)) {
+					if !pair.iter().any(|seen| seen.fact == conflict.fact) {
+						pair.push(conflict);
+					}
+				}
+				conflicts.extend(pair);
+			}
+		}
+		conflicts
+	}
+
+	/// Record a plan `agent` made without `plan`, so `conflicts` takes it into account.
+	pub fn record_plan(&mut self, agent: AgentId, plan: Plan) {
+		self.plans.insert(agent, plan);
+	}
+}
+
+/// The facts an action's preconditions and effects read or write.
+fn touched_facts(action: &Action) -> impl Iterator<Item = FactId> + '_ {
+	action
+		.preconditions
+		.iter()
+		.flat_map(Condition::referenced_facts)
+		.chain(action.effects.iter().flat_map(Effect::referenced_facts))
+}
+
+/// The actions of `plan`, skipping any that no longer exist.
+fn plan_steps<'a>(planner: &'a Planner, plan: &Plan) -> Vec<(usize, &'a Action)> {
+	plan.actions
+		.iter()
+		.enumerate()
+		.filter_map(|(step, id)| Some((step, planner.get_action(*id)?)))
+		.collect()
+}
+
+/// A conflict for each fact `writer` writes that `other` touches, at the first steps doing so.
+fn plan_conflicts<'a>(
+	(writer, writer_steps): (AgentId, &'a [(usize, &'a Action)]),
+	(other, other_steps): (AgentId, &'a [(usize, &'a Action)]),
+) -> impl Iterator<Item = Conflict> + 'a {
+	let mut written: Vec<(FactId, usize)> = Vec::new();
+	for (step, action) in writer_steps {
+		for effect in &action.effects {
+			if !written.iter().any(|(fact, _)| *fact == effect.fact()) {
+				written.push((effect.fact(), *step));
+			}
+		}
+	}
+	written.into_iter().filter_map(move |(fact, writer_step)| {
+		let (other_step, _) = other_steps
+			.iter()
+			.find(|(_, action)| touched_facts(action).any(|touched| touched == fact))?;
+		Some(Conflict {
+			fact,
+			writer: (writer, writer_step),
+			other: (other, *other_step),
+		})
+	})
+}
@@ -0,0 +1,84 @@
+use crate::effect::Effect;
+use crate::fact::FactId;
+use crate::value::Value;
+use crate::world::WorldState;
+
+/// One fact that differs between two `WorldState`s, as returned by `WorldState::diff`.
+#[derive(Clone, Copy, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FactChange {
+	pub fact: FactId,
+	/// `Value::Error` if the fact didn't exist in the older state.
+	pub old: Value,
+	pub new: Value,
+}
+
+impl FactChange {
+	/// The `Effect::Set` that turns the old state into the new one for this fact.
+	#[must_use]
+	pub fn to_effect(&self) -> Effect {
+		Effect::Set(self.fact, self.new)
+	}
+
+	/// The same change in the other direction, for undoing a patch.
+	#[must_use]
+	pub fn reverted(&self) -> Self {
+		Self {
+			fact: self.fact,
+			old: self.new,
+			new: self.old,
+		}
+	}
+}
+
+impl WorldState {
+	/// Every fact of `other` whose stored value differs from this state's, in `FactId` order.
+	///
+	/// Values are compared as stored, not resolved: a `Value::Ref` that happens to resolve to the
+	/// same number is still a change, so the patch reproduces `other` exactly.
+	/// Facts this state has beyond `other.len()` are not reported; a patch never shrinks a state.
+	#[must_use]
+	pub fn diff(&self, other: &WorldState) -> Vec<FactChange> {
+		other
+			.values()
+			.enumerate()
+			.filter_map(|(index, new)| {
+				let fact = FactId(u16::try_from(index).unwrap_or(u16::MAX));
+				let old = self.try_get(fact).unwrap_or(Value::Error);
+				(!identical(old, new)).then_some(FactChange { fact, old, new })
+			})
+			.collect()
+	}
+
+	/// `diff` as a list of `Effect::Set`, to be applied with `apply_patch`.
+	#[must_use]
+	pub fn patch_to(&self, other: &WorldState) -> Vec<Effect> {
+		self.diff(other).iter().map(FactChange::to_effect).collect()
+	}
+
+	/// Apply `patch` like `apply_effects`, except that an `Effect::Set` of a fact beyond `len()`
+	/// grows the state instead of panicking.
+	///
+	/// # Panics
+	/// If an `Effect::Add` or `Effect::Sub` refers to a fact out of range.
+	pub fn apply_patch(&mut self, patch: &[Effect]) -> &mut Self {
+		for effect in patch {
+			match *effect {
+				Effect::Set(fact, value) => {
+					self.push_fact(fact, value);
+				}
+				Effect::Add(..) | Effect::Sub(..) => effect.apply(self),
+			}
+		}
+		self
+	}
+}
+
+fn identical(a: Value, b: Value) -> bool {
+	match (a, b) {
+		(Value::Int(a), Value::Int(b)) => a == b,
+		(Value::Ref(a), Value::Ref(b)) => a == b,
+		(Value::Error, Value::Error) => true,
+		_ => false,
+	}
+}
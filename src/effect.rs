@@ -1,4 +1,5 @@
-use crate::fact::FactId;
+use crate::errors::StateError;
+use crate::fact::{FactId, FactRemap};
 use crate::value::Value;
 use crate::world::WorldState;
 
@@ -11,19 +12,71 @@ pub enum Effect {
 }
 
 impl Effect {
+	#[must_use]
+	pub fn fact(&self) -> FactId {
+		match *self {
+			Effect::Set(fact, _) | Effect::Add(fact, _) | Effect::Sub(fact, _) => fact,
+		}
+	}
+
+	#[must_use]
+	pub fn value(&self) -> Value {
+		match *self {
+			Effect::Set(_, value) | Effect::Add(_, value) | Effect::Sub(_, value) => value,
+		}
+	}
+
+	#[must_use]
+	pub fn remapped(&self, remap: &FactRemap) -> Effect {
+		let (fact, value) = (remap.get(self.fact()), self.value().remapped(remap));
+		match self {
+			Effect::Set(..) => Effect::Set(fact, value),
+			Effect::Add(..) => Effect::Add(fact, value),
+			Effect::Sub(..) => Effect::Sub(fact, value),
+		}
+	}
+
+	/// Every fact this effect touches: the one it writes, and the one its value refers to.
+	pub fn referenced_facts(&self) -> impl Iterator<Item = FactId> {
+		core::iter::once(self.fact()).chain(self.value().ref_fact())
+	}
+
+	/// The operator as written by `FactMap::parse_effect`, e.g. `"+="`.
+	#[must_use]
+	pub fn operator(&self) -> &'static str {
+		match self {
+			Effect::Set(..) => "=",
+			Effect::Add(..) => "+=",
+			Effect::Sub(..) => "-=",
+		}
+	}
+
+	/// # Panics
+	/// If the effect's fact is out of range for `state`; see `try_apply`.
 	pub fn apply(&self, state: &mut WorldState) {
+		state.set(self.fact(), self.evaluate(state));
+	}
+
+	/// The value this effect would write to its fact, without modifying `state`.
+	///
+	/// # Panics
+	/// If the effect's fact is out of range for `state`.
+	#[must_use]
+	pub fn evaluate(&self, state: &WorldState) -> Value {
 		match *self {
-			Effect::Set(fact, value) => {
-				state.values[usize::from(fact.0)] = value;
-			}
-			Effect::Add(fact, delta) => {
-				let index = usize::from(fact.0);
-				state.values[index] = state.values[index].add(&delta, state);
-			}
-			Effect::Sub(fact, delta) => {
-				let index = usize::from(fact.0);
-				state.values[index] = state.values[index].sub(&delta, state);
-			}
+			Effect::Set(_, value) => value,
+			Effect::Add(fact, delta) => state.get(fact).add(&delta, state),
+			Effect::Sub(fact, delta) => state.get(fact).sub(&delta, state),
 		}
 	}
+
+	/// `apply`, or an error (leaving `state` untouched) if the effect's fact is out of range.
+	///
+	/// # Errors
+	/// - `StateError::FactOutOfRange`: if the effect's fact is not below `state.len()`
+	pub fn try_apply(&self, state: &mut WorldState) -> Result<(), StateError> {
+		state.check_fact(self.fact())?;
+		self.apply(state);
+		Ok(())
+	}
 }
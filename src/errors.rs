@@ -1,5 +1,14 @@
+use core::fmt::Write;
+use core::ops::Range;
+
 use thiserror::Error;
 
+use crate::action::ActionId;
+use crate::condition::Condition;
+use crate::coordination::AgentId;
+use crate::fact::FactId;
+use crate::htn::TaskId;
+
 #[allow(dead_code)]
 #[derive(Debug, Error)]
 pub enum PlannerError {
@@ -8,6 +17,48 @@ pub enum PlannerError {
 
 	#[error("planner produced an unreachable state between steps")]
 	UnreachableState,
+
+	#[error("fact {fact:?} is referenced, but the state only has {len} facts")]
+	IncompatibleState { fact: FactId, len: usize },
+
+	#[error("condition {0:?} compares an erroneous value")]
+	ErroneousCondition(Condition),
+}
+
+#[derive(Debug, Error)]
+pub enum HtnError {
+	#[error("no choice of methods decomposes the task")]
+	NoDecomposition,
+
+	#[error("task {0:?} does not exist")]
+	UnknownTask(TaskId),
+
+	#[error("action {0:?} does not exist")]
+	UnknownAction(ActionId),
+
+	#[error("gave up after decomposing {0} tasks")]
+	StepLimit(usize),
+
+	#[error(transparent)]
+	Planner(#[from] PlannerError),
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Error)]
+pub enum ReservationError {
+	#[error("fact {0:?} is not a reservable resource")]
+	NotAResource(FactId),
+
+	#[error("fact {fact:?} is reserved by agent {by:?}")]
+	Reserved { fact: FactId, by: AgentId },
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Error)]
+pub enum StateError {
+	#[error("fact {fact:?} is out of range for a state of {len} facts")]
+	FactOutOfRange { fact: FactId, len: usize },
+
+	#[error("facts {0:?} refer to each other in a cycle")]
+	RefCycle(Vec<FactId>),
 }
 
 #[allow(dead_code)]
@@ -16,47 +67,316 @@ pub enum NewFactError {
 	#[error("the provided fact name is an empty string, which is disallowed")]
 	EmptyFactName,
 
-	#[error("the provided fact name starts with an initial digit, which is disallowed")]
+	#[error(
+		"the provided fact name (or one of its segments) starts with a digit, which is disallowed"
+	)]
 	InitialDigitFactName,
 
-	#[error("the provided fact name contains ascii whitespace, which is disallowed")]
+	/// Any whitespace, not only ASCII; the name is kept for compatibility.
+	#[error("the provided fact name contains whitespace, which is disallowed")]
 	ContainsAsciiWhitespaceFactName,
 
+	#[error(
+		"the provided fact name contains `{0}`, but only letters, digits, `_`, and `.` are allowed"
+	)]
+	InvalidCharacterFactName(char),
+
+	#[error("the provided fact name has an empty `.` separated segment, which is disallowed")]
+	EmptySegmentFactName,
+
+	#[error("the provided fact name `{0}` is a reserved keyword")]
+	ReservedFactName(String),
+
 	#[error("the provided fact name already exists")]
 	DuplicateFactName,
 
+	#[error("the provided default value {0} is outside of the fact's range")]
+	DefaultOutOfRange(i64),
+
 	#[error("no remaining values for FactId exist")]
 	OutOfFactIdSpace,
 }
 
 #[allow(dead_code)]
 #[derive(Debug, Error)]
-pub enum ParseConditionError {
-	#[error("wrong field count")]
-	WrongFieldCount,
+pub enum MergeFactsError {
+	#[error("fact names exist in both maps: {}", .0.join(", "))]
+	Collision(Vec<String>),
+
+	#[error("scope: {0}")]
+	InvalidScope(NewFactError),
+
+	#[error("no remaining values for FactId exist")]
+	OutOfFactIdSpace,
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Error)]
+pub enum FactRangeError {
+	#[error("`{condition}` can never be satisfied, `{fact}` is in {min}..={max}")]
+	Unsatisfiable {
+		condition: String,
+		fact: String,
+		min: i64,
+		max: i64,
+	},
 
-	#[error("unrecognized operator")]
-	UnrecognizedOperator,
+	#[error(
+		"`{condition}` compares against a constant outside of {min}..={max}, the range of `{fact}`"
+	)]
+	OutOfRange {
+		condition: String,
+		fact: String,
+		min: i64,
+		max: i64,
+	},
+}
 
-	#[error("lhs is not a fact id")]
-	LhsNotAFactId,
+#[derive(Clone, Debug, Eq, PartialEq, Error)]
+pub enum FactAccessError {
+	#[error("unknown fact \"{name}\"{}", .suggestion.as_ref().map(|s| format!("; did you mean \"{s}\"?")).unwrap_or_default())]
+	UnknownFact {
+		name: String,
+		suggestion: Option<String>,
+	},
 
-	#[error("lhs is not a fact name or id")]
-	RhsNotAFactNameOrId,
+	#[error("fact \"{name}\" ({fact:?}) is out of range for a state of {len} facts")]
+	OutOfRange {
+		name: String,
+		fact: FactId,
+		len: usize,
+	},
 }
 
+/// Returned by `FactMap::format_value` and friends for things the parser can't read back.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Error)]
+pub enum FormatError {
+	#[error("Value::Error has no syntax")]
+	ErrorValue,
+
+	#[error("fact {0:?} is not in the FactMap")]
+	UnknownFact(FactId),
+}
+
+#[derive(Clone, Debug, Eq, PartialEq, Error)]
+pub enum ParseErrorKind {
+	#[error("unexpected end of input, expected {0}")]
+	UnexpectedEnd(&'static str),
+
+	#[error("unexpected `{found}`, expected {expected}")]
+	UnexpectedToken {
+		found: String,
+		expected: &'static str,
+	},
+
+	#[error("unexpected character `{0}`")]
+	UnexpectedCharacter(char),
+
+	#[error("`{0}` is not a valid integer")]
+	InvalidInteger(String),
+
+	#[error("unrecognized operator `{0}`")]
+	UnrecognizedOperator(String),
+
+	#[error("lhs `{0}` is not a fact name")]
+	LhsNotAFactId(String),
+
+	#[error("rhs `{0}` is not a fact name or integer")]
+	RhsNotAFactNameOrId(String),
+}
+
+/// An error from `FactMap::parse_condition` or `FactMap::parse_effect`.
+///
+/// Carries the whole input and the byte `span` of the offending token so it can be shown to a designer
+/// with `render`, plus a "did you mean" `suggestion` when a close match exists.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ParseError {
+	pub kind: ParseErrorKind,
+	pub input: String,
+	pub span: Range<usize>,
+	pub suggestion: Option<String>,
+}
+
+pub type ParseConditionError = ParseError;
+pub type ParseEffectError = ParseError;
+
+impl ParseError {
+	#[must_use]
+	pub fn new(kind: ParseErrorKind, input: impl Into<String>, span: Range<usize>) -> Self {
+		Self {
+			kind,
+			input: input.into(),
+			span,
+			suggestion: None,
+		}
+	}
+
+	#[must_use]
+	pub fn with_suggestion(mut self, suggestion: Option<impl Into<String>>) -> Self {
+		self.suggestion = suggestion.map(Into::into);
+		self
+	}
+
+	/// The text covered by `span`.
+	#[must_use]
+	pub fn snippet(&self) -> &str {
+		self.input.get(self.span.clone()).unwrap_or_default()
+	}
+
+	/// Render a multi-line caret diagnostic, such as:
+	/// ```text
+	/// error: lhs `hungr` is not a fact name
+	///   | hungr >= 3
+	///   | ^^^^^
+	///   = help: did you mean `hunger`?
+	/// ```
+	#[must_use]
+	pub fn render(&self) -> String {
+		let start = self.input[..self.span.start.min(self.input.len())]
+			.chars()
+			.count();
+		let width = self.snippet().chars().count().max(1);
+		let mut out = format!(
+			"error: {}\n  | {}\n  | {}{}",
+			self.kind,
+			self.input,
+			" ".repeat(start),
+			"^".repeat(width)
+		);
+		if let Some(suggestion) = &self.suggestion {
+			let _ = write!(out, "\n  = help: did you mean `{suggestion}`?");
+		}
+		out
+	}
+}
+
+impl core::fmt::Display for ParseError {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> core::fmt::Result {
+		write!(f, "{}", self.kind)?;
+		if let Some(suggestion) = &self.suggestion {
+			write!(f, "; did you mean `{suggestion}`?")?;
+		}
+		Ok(())
+	}
+}
+
+impl core::error::Error for ParseError {}
+
 #[allow(dead_code)]
 #[derive(Debug, Error)]
-pub enum ParseEffectError {
-	#[error("wrong field count")]
-	WrongFieldCount,
+pub enum ExecutorError {
+	#[error("the executor kind is an empty string, which is disallowed")]
+	EmptyKind,
+
+	#[error("the executor kind \"{0}\" contains whitespace, which is disallowed")]
+	WhitespaceInKind(String),
+
+	#[error("executor argument {0} is a parameter with an empty name")]
+	EmptyParam(usize),
+
+	#[error("executor argument {0} refers to fact {1:?}, which is out of range")]
+	FactOutOfRange(usize, FactId),
+
+	#[error("executor argument refers to unknown fact \"{0}\"")]
+	UnknownFact(String),
+}
+
+#[cfg(all(feature = "simple", feature = "serde"))]
+#[derive(Clone, Debug, Eq, PartialEq, Error)]
+pub enum ExportError {
+	#[error("initial_state sets \"{0}\" to a reference or error, which is not a literal")]
+	InitialNotALiteral(String),
+
+	#[error("action \"{action}\", preconditions[{index}]: {source}")]
+	Precondition {
+		action: String,
+		index: usize,
+		source: FormatError,
+	},
+
+	#[error("action \"{action}\", effects[{index}]: {source}")]
+	Effect {
+		action: String,
+		index: usize,
+		source: FormatError,
+	},
+
+	#[error("action \"{action}\", executors[{index}]: {source}")]
+	Executor {
+		action: String,
+		index: usize,
+		source: FormatError,
+	},
+
+	#[error("goal \"{goal}\", conditions[{index}]: {source}")]
+	GoalCondition {
+		goal: String,
+		index: usize,
+		source: FormatError,
+	},
+}
+
+#[cfg(all(feature = "simple", feature = "serde"))]
+#[derive(Debug, Error)]
+pub enum DomainError {
+	#[cfg(feature = "json")]
+	#[error("invalid JSON: {0}")]
+	Json(#[from] serde_json::Error),
+
+	#[cfg(feature = "ron")]
+	#[error("invalid RON: {0}")]
+	Ron(#[from] ron::error::SpannedError),
+
+	#[error("fact \"{name}\": {source}")]
+	Fact { name: String, source: NewFactError },
+
+	#[error("initial_state refers to unknown fact \"{0}\"")]
+	UnknownInitialFact(String),
+
+	#[error("initial_state sets \"{name}\" to {value}, which is outside of its range")]
+	InitialOutOfRange { name: String, value: i64 },
+
+	#[error("action \"{action}\", cost: \"{cost}\" is not an integer")]
+	Cost { action: String, cost: String },
+
+	#[error("action \"{action}\", preconditions[{index}]: {source}")]
+	Precondition {
+		action: String,
+		index: usize,
+		source: Box<ParseConditionError>,
+	},
+
+	#[error("action \"{action}\", preconditions[{index}]: {source}")]
+	PreconditionRange {
+		action: String,
+		index: usize,
+		source: FactRangeError,
+	},
+
+	#[error("action \"{action}\", effects[{index}]: {source}")]
+	Effect {
+		action: String,
+		index: usize,
+		source: Box<ParseEffectError>,
+	},
 
-	#[error("unrecognized operator")]
-	UnrecognizedOperator,
+	#[error("action \"{action}\", executors[{index}]: {source}")]
+	Executor {
+		action: String,
+		index: usize,
+		source: ExecutorError,
+	},
 
-	#[error("lhs is not a fact id")]
-	LhsNotAFactId,
+	#[error("goal \"{goal}\", conditions[{index}]: {source}")]
+	GoalCondition {
+		goal: String,
+		index: usize,
+		source: Box<ParseConditionError>,
+	},
 
-	#[error("lhs is not a fact name or id")]
-	RhsNotAFactNameOrId,
+	#[error("goal \"{goal}\", conditions[{index}]: {source}")]
+	GoalConditionRange {
+		goal: String,
+		index: usize,
+		source: FactRangeError,
+	},
 }
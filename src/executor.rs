@@ -0,0 +1,222 @@
+use crate::errors::ExecutorError;
+use crate::fact::{FactId, FactRemap};
+
+/// A single structured argument of an [`Executor`].
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ExecutorArg {
+	Int(i64),
+	Text(String),
+	/// A reference to a fact, resolved against the agent's `WorldState` at execution time.
+	Fact(FactId),
+	/// A named parameter bound by the caller at execution time (written as `$name`).
+	Param(String),
+}
+
+impl ExecutorArg {
+	/// Parse a single argument without a `FactMap`:
+	/// `"3"` => `ExecutorArg::Int(3)`
+	/// `"$target"` => `ExecutorArg::Param("target")`
+	/// `"@#2"` => `ExecutorArg::Fact(FactId(2))`
+	/// `"tall_grass"` => `ExecutorArg::Text("tall_grass")`
+	///
+	/// Text is kept as is, including any surrounding whitespace.
+	#[must_use]
+	pub fn parse(string: &str) -> Self {
+		let trimmed = string.trim();
+		if let Ok(int) = trimmed.parse::<i64>() {
+			ExecutorArg::Int(int)
+		} else if let Some(param) = trimmed.strip_prefix('$') {
+			ExecutorArg::Param(param.into())
+		} else if let Some(fact) = trimmed
+			.strip_prefix("@#")
+			.and_then(|id| id.parse::<u16>().ok())
+		{
+			ExecutorArg::Fact(FactId(fact))
+		} else {
+			ExecutorArg::Text(string.into())
+		}
+	}
+
+	#[must_use]
+	pub fn remapped(&self, remap: &FactRemap) -> Self {
+		match self {
+			ExecutorArg::Fact(fact) => ExecutorArg::Fact(remap.get(*fact)),
+			arg => arg.clone(),
+		}
+	}
+
+	/// Parse a `:` separated argument list such as `"4:8"` with [`ExecutorArg::parse`].
+	/// An empty (or all whitespace) string yields no arguments.
+	///
+	/// A `\` makes the next character literal: `"a\:b"` is the single argument `a:b`, and an argument
+	/// starting with an escaped character is always `ExecutorArg::Text`, e.g. `"\3"` or `"\$name"`.
+	/// This is how `Display` writes text that would otherwise parse differently.
+	#[must_use]
+	pub fn parse_list(string: &str) -> Vec<Self> {
+		Self::parse_list_escaped(string)
+			.into_iter()
+			.map(|(arg, _)| arg)
+			.collect()
+	}
+
+	/// `parse_list`, along with whether each argument started with an escaped character.
+	pub(crate) fn parse_list_escaped(string: &str) -> Vec<(Self, bool)> {
+		if string.trim().is_empty() {
+			return Vec::new();
+		}
+		let mut args = Vec::new();
+		let mut arg = String::new();
+		let mut escaped = false;
+		let mut chars = string.chars();
+		while let Some(c) = chars.next() {
+			match c {
+				'\\' => {
+					escaped |= arg.is_empty();
+					arg.extend(chars.next());
+				}
+				':' => args.push(Self::finish(&mut arg, &mut escaped)),
+				c => arg.push(c),
+			}
+		}
+		args.push(Self::finish(&mut arg, &mut escaped));
+		args
+	}
+
+	fn finish(arg: &mut String, escaped: &mut bool) -> (Self, bool) {
+		let arg = core::mem::take(arg);
+		if core::mem::take(escaped) {
+			(ExecutorArg::Text(arg), true)
+		} else {
+			(Self::parse(&arg), false)
+		}
+	}
+}
+
+/// Written so that [`ExecutorArg::parse_list`] reads it back as the same argument.
+impl core::fmt::Display for ExecutorArg {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> core::fmt::Result {
+		match self {
+			ExecutorArg::Int(int) => write!(f, "{int}"),
+			ExecutorArg::Text(text) => {
+				// text that would parse as something else (or as `@name` in `FactMap::parse_executor`)
+				// starts with an escape
+				if text.trim().starts_with('@')
+					|| !matches!(ExecutorArg::parse(text), ExecutorArg::Text(_))
+				{
+					write!(f, "\\")?;
+				}
+				write_escaped(f, text)
+			}
+			ExecutorArg::Fact(fact) => write!(f, "@#{}", fact.0),
+			ExecutorArg::Param(param) => {
+				write!(f, "$")?;
+				write_escaped(f, param)
+			}
+		}
+	}
+}
+
+fn write_escaped(f: &mut std::fmt::Formatter<'_>, string: &str) -> core::fmt::Result {
+	for c in string.chars() {
+		if matches!(c, '\\' | ':') {
+			write!(f, "\\")?;
+		}
+		write!(f, "{c}")?;
+	}
+	Ok(())
+}
+
+/// Describes one step the game should run to carry out an `Action`, e.g. `approach(3)`.
+/// The planner never looks at executors; they are data for the caller.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Executor {
+	pub kind: String,
+	#[cfg_attr(feature = "serde", serde(default))]
+	pub args: Vec<ExecutorArg>,
+}
+
+impl Executor {
+	pub fn new(kind: impl Into<String>) -> Self {
+		Self {
+			kind: kind.into(),
+			args: Vec::new(),
+		}
+	}
+
+	pub fn push_arg(&mut self, arg: ExecutorArg) -> &mut Self {
+		self.args.push(arg);
+		self
+	}
+
+	#[must_use]
+	pub fn with_arg(mut self, arg: ExecutorArg) -> Self {
+		self.args.push(arg);
+		self
+	}
+
+	#[must_use]
+	pub fn remapped(&self, remap: &FactRemap) -> Self {
+		Self {
+			kind: self.kind.clone(),
+			args: self.args.iter().map(|arg| arg.remapped(remap)).collect(),
+		}
+	}
+
+	/// # Errors
+	/// - `ExecutorError::EmptyKind`: if the kind is an empty string
+	/// - `ExecutorError::WhitespaceInKind`: if the kind contains whitespace
+	/// - `ExecutorError::EmptyParam`: if an `ExecutorArg::Param` has an empty name
+	/// - `ExecutorError::FactOutOfRange`: if an `ExecutorArg::Fact` is not below `num_facts`
+	pub fn validate(&self, num_facts: usize) -> Result<(), ExecutorError> {
+		if self.kind.is_empty() {
+			return Err(ExecutorError::EmptyKind);
+		}
+		if self.kind.contains(char::is_whitespace) {
+			return Err(ExecutorError::WhitespaceInKind(self.kind.clone()));
+		}
+		for (index, arg) in self.args.iter().enumerate() {
+			match arg {
+				ExecutorArg::Param(param) if param.is_empty() => {
+					return Err(ExecutorError::EmptyParam(index));
+				}
+				ExecutorArg::Fact(fact) if usize::from(fact.0) >= num_facts => {
+					return Err(ExecutorError::FactOutOfRange(index, *fact));
+				}
+				_ => {}
+			}
+		}
+		Ok(())
+	}
+}
+
+/// Legacy `(kind, args)` pairs; `args` is parsed with [`ExecutorArg::parse_list`].
+impl From<(String, String)> for Executor {
+	fn from(value: (String, String)) -> Self {
+		Self {
+			args: ExecutorArg::parse_list(&value.1),
+			kind: value.0,
+		}
+	}
+}
+
+impl From<(&str, &str)> for Executor {
+	fn from(value: (&str, &str)) -> Self {
+		Self {
+			kind: value.0.into(),
+			args: ExecutorArg::parse_list(value.1),
+		}
+	}
+}
+
+impl core::fmt::Display for Executor {
+	fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> core::fmt::Result {
+		write!(f, "{}", self.kind)?;
+		for (index, arg) in self.args.iter().enumerate() {
+			let separator = if index == 0 { " " } else { ":" };
+			write!(f, "{separator}{arg}")?;
+		}
+		Ok(())
+	}
+}
@@ -0,0 +1,160 @@
+use std::collections::{HashSet, VecDeque};
+
+use crate::action::{Action, ActionId};
+use crate::condition::{Condition, conditions_met};
+use crate::errors::PlannerError;
+use crate::goal::Goal;
+use crate::options::PlanOptions;
+use crate::planner::Planner;
+use crate::world::WorldState;
+
+/// Returned by `Planner::explain`: why a goal can or can't be reached from a start state.
+#[derive(Clone, Debug)]
+pub struct Explanation {
+	/// Whether some explored state satisfies the goal, i.e. `Planner::plan` would succeed.
+	pub goal_reached: bool,
+	/// Number of distinct states explored, including the start.
+	pub states_explored: usize,
+	/// `false` if exploring stopped at `Planner::EXPLAIN_MAX_STATES` before every reachable state was seen.
+	pub exhausted: bool,
+	/// Goal conditions that are false at the start, and whose fact no action has an effect on.
+	pub unachievable: Vec<Condition>,
+	/// Goal conditions that are false in every explored state.
+	pub never_satisfied: Vec<Condition>,
+	/// Actions whose preconditions were not met in any explored state.
+	pub never_applicable: Vec<BlockedAction>,
+}
+
+/// An action that could never be applied, and its precondition that failed in the most explored states.
+#[derive(Clone, Copy, Debug)]
+pub struct BlockedAction {
+	pub action: ActionId,
+	pub precondition: Condition,
+	/// Number of explored states in which `precondition` was not satisfied.
+	pub blocked: usize,
+}
+
+impl Planner {
+	/// How many states `explain` explores at most.
+	pub const EXPLAIN_MAX_STATES: usize = 100_000;
+
+	/// Explore every state reachable from `start` (up to `EXPLAIN_MAX_STATES`) and report which goal
+	/// conditions and actions stood in the way. This is much slower than `plan`; use it to diagnose a
+	/// `PlannerError::NoPlanFound`. Only actions the goal's `allowed_tags` allow are considered.
+	///
+	/// # Errors
+	/// - `PlannerError::IncompatibleState`: if an action or the goal references a fact `start` doesn't have
+	pub fn explain(&self, start: &WorldState, goal: &Goal) -> Result<Explanation, PlannerError> {
+		self.explain_with(start, goal, &PlanOptions::default())
+	}
+
+	/// `explain` for `plan_with`: only with the actions `options` allows. Cost overrides and
+	/// modifiers don't change which states are reachable, so they are ignored.
+	///
+	/// # Errors
+	/// See `explain`.
+	pub fn explain_with(
+		&self,
+		start: &WorldState,
+		goal: &Goal,
+		options: &PlanOptions,
+	) -> Result<Explanation, PlannerError> {
+		self.check_compatible(start)?;
+		self.check_goal_compatible(start, goal)?;
+
+		let actions: Vec<(ActionId, &Action)> = self
+			.actions()
+			.filter(|(id, action)| options.allows(*id) && goal.allows(action))
+			.collect();
+		let mut satisfied = vec![false; goal.conditions.len()];
+		let mut applied = vec![false; actions.len()];
+		// per action, per precondition: number of states in which it failed
+		let mut blocked: Vec<Vec<usize>> = actions
+			.iter()
+			.map(|(_, action)| vec![0; action.preconditions.len()])
+			.collect();
+
+		let mut goal_reached = false;
+		let mut exhausted = true;
+		let mut seen = HashSet::from([start.clone()]);
+		let mut queue = VecDeque::from([start.clone()]);
+		while let Some(state) = queue.pop_front() {
+			for (condition, satisfied) in goal.conditions.iter().zip(&mut satisfied) {
+				*satisfied |= condition.is_satisfied(&state);
+			}
+			goal_reached |= conditions_met(&goal.conditions, &state);
+
+			for (index, (_, action)) in actions.iter().enumerate() {
+				let mut applicable = true;
+				for (condition, count) in action.preconditions.iter().zip(&mut blocked[index]) {
+					if !condition.is_satisfied(&state) {
+						*count += 1;
+						applicable = false;
+					}
+				}
+				if !applicable {
+					continue;
+				}
+				applied[index] = true;
+				let next = self.next_state(&state, action);
+				if seen.contains(&next) {
+					continue;
+				}
+				if seen.len() < Self::EXPLAIN_MAX_STATES {
+					seen.insert(next.clone());
+					queue.push_back(next);
+				} else {
+					exhausted = false;
+				}
+			}
+		}
+
+		let unachievable = goal
+			.conditions
+			.iter()
+			.filter(|condition| {
+				!condition.is_satisfied(start)
+					&& !actions.iter().any(|(_, action)| {
+						action
+							.effects
+							.iter()
+							.any(|effect| effect.fact() == condition.fact())
+					})
+			})
+			.copied()
+			.collect();
+		let never_satisfied = goal
+			.conditions
+			.iter()
+			.zip(&satisfied)
+			.filter(|(_, satisfied)| !**satisfied)
+			.map(|(condition, _)| *condition)
+			.collect();
+		let never_applicable = actions
+			.iter()
+			.enumerate()
+			.filter(|(index, _)| !applied[*index])
+			.filter_map(|(index, (id, action))| {
+				let (precondition, count) = action
+					.preconditions
+					.iter()
+					.zip(&blocked[index])
+					.reduce(|most, next| if next.1 > most.1 { next } else { most })?;
+				Some(BlockedAction {
+					action: *id,
+					precondition: *precondition,
+					blocked: *count,
+				})
+			})
+			.collect();
+
+		Ok(Explanation {
+			goal_reached,
+			states_explored: seen.len(),
+			exhausted,
+			unachievable,
+			never_satisfied,
+			never_applicable,
+		})
+	}
+}
@@ -1,3 +1,87 @@
 #[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct FactId(pub u16);
+
+/// The inclusive range of integers a fact may hold; effects on the fact are clamped to it.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FactRange {
+	pub min: i64,
+	pub max: i64,
+}
+
+impl FactRange {
+	pub const UNBOUNDED: FactRange = FactRange {
+		min: i64::MIN,
+		max: i64::MAX,
+	};
+	pub const BOOL: FactRange = FactRange { min: 0, max: 1 };
+
+	#[must_use]
+	pub fn new(min: i64, max: i64) -> Self {
+		Self { min, max }
+	}
+
+	#[must_use]
+	pub fn contains(&self, int: i64) -> bool {
+		self.min <= int && int <= self.max
+	}
+
+	#[must_use]
+	pub fn clamp(&self, int: i64) -> i64 {
+		int.max(self.min).min(self.max)
+	}
+
+	#[must_use]
+	pub fn intersect(&self, other: &FactRange) -> FactRange {
+		FactRange {
+			min: self.min.max(other.min),
+			max: self.max.min(other.max),
+		}
+	}
+}
+
+impl Default for FactRange {
+	fn default() -> Self {
+		Self::UNBOUNDED
+	}
+}
+
+/// Maps the `FactId`s of one id space onto another, e.g. after merging two `FactMap`s.
+/// Ids without an entry are left unchanged.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FactRemap {
+	map: Vec<FactId>, // indexed by the old FactId
+}
+
+impl FactRemap {
+	#[must_use]
+	pub fn new(map: Vec<FactId>) -> Self {
+		Self { map }
+	}
+
+	#[inline]
+	#[must_use]
+	pub fn get(&self, fact: FactId) -> FactId {
+		self.map.get(usize::from(fact.0)).copied().unwrap_or(fact)
+	}
+
+	#[must_use]
+	pub fn len(&self) -> usize {
+		self.map.len()
+	}
+
+	#[must_use]
+	pub fn is_empty(&self) -> bool {
+		self.map.is_empty()
+	}
+
+	/// `(old, new)` pairs for every mapped id.
+	pub fn iter(&self) -> impl Iterator<Item = (FactId, FactId)> + '_ {
+		self.map
+			.iter()
+			.enumerate()
+			.map(|(old, new)| (FactId(u16::try_from(old).unwrap_or(u16::MAX)), *new))
+	}
+}
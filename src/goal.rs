@@ -1,10 +1,15 @@
+use crate::action::Action;
 use crate::condition::Condition;
+use crate::fact::{FactId, FactRemap};
 
 #[derive(Clone, Debug)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Goal {
 	pub name: String,
 	pub conditions: Vec<Condition>,
+	/// If not empty, plans for this goal only use actions with at least one of these tags.
+	#[cfg_attr(feature = "serde", serde(default))]
+	pub allowed_tags: Vec<String>,
 }
 
 impl Goal {
@@ -12,6 +17,7 @@ impl Goal {
 		Self {
 			name: name.into(),
 			conditions: Vec::new(),
+			allowed_tags: Vec::new(),
 		}
 	}
 
@@ -19,6 +25,36 @@ impl Goal {
 		self.conditions.push(condition);
 		self
 	}
+
+	pub fn push_allowed_tag(&mut self, tag: impl Into<String>) -> &mut Self {
+		self.allowed_tags.push(tag.into());
+		self
+	}
+
+	/// Whether plans for this goal may use `action`, see `allowed_tags`.
+	#[must_use]
+	pub fn allows(&self, action: &Action) -> bool {
+		self.allowed_tags.is_empty() || self.allowed_tags.iter().any(|tag| action.has_tag(tag))
+	}
+
+	/// Every fact referenced by the conditions, possibly with repeats.
+	pub fn referenced_facts(&self) -> impl Iterator<Item = FactId> + '_ {
+		self.conditions.iter().flat_map(Condition::referenced_facts)
+	}
+
+	/// A copy of this goal with every `FactId` passed through `remap`.
+	#[must_use]
+	pub fn remapped(&self, remap: &FactRemap) -> Self {
+		Self {
+			name: self.name.clone(),
+			conditions: self
+				.conditions
+				.iter()
+				.map(|condition| condition.remapped(remap))
+				.collect(),
+			allowed_tags: self.allowed_tags.clone(),
+		}
+	}
 }
 
 impl PartialEq for Goal {
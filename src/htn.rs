@@ -0,0 +1,192 @@
+use crate::action::ActionId;
+use crate::condition::{Condition, conditions_met};
+use crate::cost::Cost;
+use crate::errors::HtnError;
+use crate::plan::Plan;
+use crate::planner::Planner;
+use crate::world::WorldState;
+
+/// Identifies a compound task in an `HtnPlanner`.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TaskId(pub u32);
+
+/// A step of a method: either an action of the `Planner`, or another compound task to decompose.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Task {
+	Primitive(ActionId),
+	Compound(TaskId),
+}
+
+/// A task such as "Get Wood", with the ways of doing it in order of preference.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CompoundTask {
+	pub name: String,
+	pub methods: Vec<Method>,
+}
+
+/// One way of doing a `CompoundTask`: when `preconditions` hold, do `subtasks` in order.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Method {
+	pub name: String,
+	#[cfg_attr(feature = "serde", serde(default))]
+	pub preconditions: Vec<Condition>,
+	pub subtasks: Vec<Task>,
+}
+
+impl CompoundTask {
+	pub fn new(name: impl Into<String>) -> Self {
+		Self {
+			name: name.into(),
+			methods: Vec::new(),
+		}
+	}
+
+	pub fn push_method(&mut self, method: Method) -> &mut Self {
+		self.methods.push(method);
+		self
+	}
+}
+
+impl Method {
+	pub fn new(name: impl Into<String>) -> Self {
+		Self {
+			name: name.into(),
+			preconditions: Vec::new(),
+			subtasks: Vec::new(),
+		}
+	}
+
+	pub fn push_precondition(&mut self, precondition: Condition) -> &mut Self {
+		self.preconditions.push(precondition);
+		self
+	}
+
+	pub fn push_subtask(&mut self, subtask: Task) -> &mut Self {
+		self.subtasks.push(subtask);
+		self
+	}
+}
+
+/// Hierarchical task network planning on top of a `Planner`'s actions: compound tasks are
+/// decomposed through their methods until only actions remain, giving an ordinary `Plan`.
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HtnPlanner {
+	tasks: Vec<CompoundTask>, // indexed by TaskId
+}
+
+/// A partial decomposition: the state so far, and the tasks still to do, last one first.
+struct Node {
+	state: WorldState,
+	agenda: Vec<Task>,
+	actions: Vec<ActionId>,
+	cost: Cost,
+}
+
+impl HtnPlanner {
+	/// How many tasks `plan` decomposes at most before giving up.
+	pub const MAX_STEPS: usize = 100_000;
+
+	#[must_use]
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	pub fn push_task(&mut self, task: CompoundTask) -> TaskId {
+		self.tasks.push(task);
+		TaskId(u32::try_from(self.tasks.len() - 1).unwrap_or(u32::MAX))
+	}
+
+	#[must_use]
+	pub fn get_task(&self, id: TaskId) -> Option<&CompoundTask> {
+		self.tasks.get(usize::try_from(id.0).ok()?)
+	}
+
+	pub fn get_task_mut(&mut self, id: TaskId) -> Option<&mut CompoundTask> {
+		self.tasks.get_mut(usize::try_from(id.0).ok()?)
+	}
+
+	#[must_use]
+	pub fn get_task_id(&self, name: &str) -> Option<TaskId> {
+		self.tasks
+			.iter()
+			.position(|task| task.name == name)
+			.and_then(|index| u32::try_from(index).ok())
+			.map(TaskId)
+	}
+
+	/// Decompose `root` from `start` into actions of `planner`. Methods are tried in order, and a
+	/// method whose subtasks can't be completed is backtracked over, so the first workable
+	/// decomposition wins (not necessarily the cheapest). Actions use their own cost and must have
+	/// their preconditions met when reached; conditions comparing `Value::Error` are unsatisfied.
+	///
+	/// # Errors
+	/// - `HtnError::NoDecomposition`: if no choice of methods works out
+	/// - `HtnError::UnknownTask`, `HtnError::UnknownAction`: if a method refers to a task or action
+	///   that doesn't exist
+	/// - `HtnError::StepLimit`: after decomposing `MAX_STEPS` tasks, e.g. for a method that recurses forever
+	/// - `HtnError::Planner`: if an action references a fact `start` doesn't have
+	pub fn plan(
+		&self,
+		planner: &Planner,
+		start: &WorldState,
+		root: Task,
+	) -> Result<Plan, HtnError> {
+		planner.check_compatible(start)?;
+
+		// depth first: the node on top of the stack continues the most recent choice
+		let mut stack = vec![Node {
+			state: start.clone(),
+			agenda: vec![root],
+			actions: Vec::new(),
+			cost: Cost(0),
+		}];
+		let mut steps = 0;
+		while let Some(mut node) = stack.pop() {
+			let Some(task) = node.agenda.pop() else {
+				return Ok(Plan {
+					actions: node.actions,
+					total_cost: node.cost,
+				});
+			};
+			steps += 1;
+			if steps > Self::MAX_STEPS {
+				return Err(HtnError::StepLimit(Self::MAX_STEPS));
+			}
+
+			match task {
+				Task::Primitive(id) => {
+					let action = planner.get_action(id).ok_or(HtnError::UnknownAction(id))?;
+					if conditions_met(&action.preconditions, &node.state) {
+						node.state = planner.next_state(&node.state, action);
+						node.actions.push(id);
+						node.cost += action.cost;
+						stack.push(node);
+					}
+				}
+				Task::Compound(id) => {
+					let task = self.get_task(id).ok_or(HtnError::UnknownTask(id))?;
+					// pushed in reverse, so the first applicable method is tried first
+					for method in task.methods.iter().rev() {
+						if !conditions_met(&method.preconditions, &node.state) {
+							continue;
+						}
+						let mut agenda = node.agenda.clone();
+						agenda.extend(method.subtasks.iter().rev());
+						stack.push(Node {
+							state: node.state.clone(),
+							agenda,
+							actions: node.actions.clone(),
+							cost: node.cost,
+						});
+					}
+				}
+			}
+		}
+		Err(HtnError::NoDecomposition)
+	}
+}
@@ -2,36 +2,70 @@ mod action;
 #[cfg(feature = "bevy")]
 mod bevy;
 mod condition;
+mod coordination;
 mod cost;
+mod diff;
 mod effect;
 mod errors;
+mod executor;
+mod explain;
 mod fact;
 mod goal;
+mod htn;
+mod multi;
+mod options;
 mod plan;
 mod planner;
 #[cfg(feature = "simple")]
 mod simple;
+mod slots;
+mod trace;
 mod value;
 mod world;
 
-pub use action::Action;
-pub use condition::{Condition, conditions_met};
+pub use action::{Action, ActionId};
+pub use condition::{Condition, conditions_met, evaluate_conditions};
+pub use coordination::{AgentId, Conflict, Coordinator};
 pub use cost::Cost;
+pub use diff::FactChange;
 pub use effect::Effect;
-pub use errors::PlannerError;
-pub use fact::FactId;
+#[cfg(all(feature = "simple", feature = "serde"))]
+pub use errors::{DomainError, ExportError};
+pub use errors::{
+	ExecutorError, FactAccessError, FactRangeError, FormatError, HtnError, MergeFactsError,
+	NewFactError, ParseConditionError, ParseEffectError, ParseError, ParseErrorKind, PlannerError,
+	ReservationError, StateError,
+};
+pub use executor::{Executor, ExecutorArg};
+pub use explain::{BlockedAction, Explanation};
+pub use fact::{FactId, FactRange, FactRemap};
 pub use goal::Goal;
+pub use htn::{CompoundTask, HtnPlanner, Method, Task, TaskId};
+pub use multi::{GoalMode, MultiGoal, MultiPlan, WeightedGoal};
+pub use options::{ActionMask, CostModifier, PlanOptions};
 pub use plan::Plan;
-pub use planner::Planner;
+pub use planner::{ErrorPolicy, Planner};
 #[cfg(feature = "simple")]
 pub use simple::*;
+#[cfg(feature = "tracing")]
+pub use trace::TracingTracer;
+pub use trace::{PlanTracer, SearchEdge, SearchNode, SearchTree};
 pub use value::Value;
 pub use world::WorldState;
 
 #[cfg(test)]
 mod tests {
+	use std::hash::{Hash, Hasher};
+	use std::sync::Arc;
+
 	use super::*;
 
+	fn hash_of(state: &WorldState) -> u64 {
+		let mut hasher = std::hash::DefaultHasher::new();
+		state.hash(&mut hasher);
+		hasher.finish()
+	}
+
 	#[test]
 	fn condition_and_effects_work() {
 		let health = FactId(0);
@@ -63,6 +97,7 @@ mod tests {
 			preconditions: vec![],
 			effects: vec![Effect::Set(has_axe, Value::TRUE)],
 			executors: vec![],
+			tags: vec![],
 		};
 
 		let chop_wood = Action {
@@ -71,6 +106,7 @@ mod tests {
 			preconditions: vec![Condition::Eq(has_axe, Value::TRUE)],
 			effects: vec![Effect::Set(has_wood, Value::TRUE)],
 			executors: vec![],
+			tags: vec![],
 		};
 
 		// Goal
@@ -86,8 +122,8 @@ mod tests {
 		let plan = planner.plan(&start, &goal).expect("no plan found");
 
 		// Test typical usage:
-		for index in &plan.actions {
-			if let Some(action) = planner.get_plan_action(&plan, *index) {
+		for id in &plan.actions {
+			if let Some(action) = planner.get_action(*id) {
 				println!("{}", action.name);
 			}
 		}
@@ -109,4 +145,784 @@ mod tests {
 			"Chop Wood"
 		);
 	}
+
+	#[test]
+	fn legacy_executor_pairs_are_parsed() {
+		let mut action = Action::new("Wander", Cost(1));
+		action.push_executor("locate_passable", "4:8");
+		action.push_executor("locate", "tall_grass");
+		action.push_executor("recall_located", "$escape");
+
+		assert_eq!(
+			action.executors[0].args,
+			vec![ExecutorArg::Int(4), ExecutorArg::Int(8)]
+		);
+		assert_eq!(
+			action.executors[1].args,
+			vec![ExecutorArg::Text("tall_grass".into())]
+		);
+		assert_eq!(
+			action.executors[2].args,
+			vec![ExecutorArg::Param("escape".into())]
+		);
+		assert!(action.executors.iter().all(|e| e.validate(0).is_ok()));
+	}
+
+	#[test]
+	fn effects_are_clamped_to_fact_ranges() {
+		let energy = FactId(0);
+		let start = WorldState::new(1).with_fact(energy, Value::Int(25));
+
+		let mut rest = Action::new("Rest", Cost(1));
+		rest.push_effect(Effect::Add(energy, Value::Int(1)));
+
+		let mut planner = Planner::new();
+		planner.push_action(rest);
+		planner.set_fact_ranges(vec![FactRange::new(0, 26)]);
+
+		let ranges = planner.get_fact_ranges();
+		assert!(start.is_changed_by(&[Effect::Add(energy, Value::Int(1))], ranges));
+		let full = start.with_fact(energy, Value::Int(26));
+		assert!(!full.is_changed_by(&[Effect::Add(energy, Value::Int(1))], ranges));
+		assert!(!full.is_changed_by(&[Effect::Set(energy, Value::Int(26))], ranges));
+
+		// each ref alone resolves to 5, together they form a cycle
+		let (x, y) = (FactId(0), FactId(1));
+		let fives = WorldState::new(2)
+			.with_fact(x, Value::Int(5))
+			.with_fact(y, Value::Int(5));
+		let swap = [Effect::Set(x, Value::Ref(y)), Effect::Set(y, Value::Ref(x))];
+		assert!(fives.is_changed_by(&swap, &[]));
+		assert_ne!(fives, fives.with_effects(&swap));
+		let mut link = Action::new("Link", Cost(1));
+		link.push_effect(swap[0]).push_effect(swap[1]);
+		let mut linker = Planner::new();
+		linker.push_action(link);
+		let mut broken = Goal::new("Broken");
+		broken.push_condition(Condition::Ne(x, Value::Int(5)));
+		linker.set_error_policy(ErrorPolicy::Fail);
+		// the successor is searched, so its cycle is found rather than skipped as a no-op
+		assert!(matches!(
+			linker.plan(&fives, &broken),
+			Err(PlannerError::ErroneousCondition(_))
+		));
+
+		let mut goal = Goal::new("Overcharge");
+		goal.push_condition(Condition::Ge(energy, Value::Int(27)));
+		assert!(matches!(
+			planner.plan(&start, &goal),
+			Err(PlannerError::NoPlanFound)
+		));
+	}
+
+	#[test]
+	fn incompatible_state_is_an_error() {
+		let hunger = FactId(0);
+		let satiety = FactId(3);
+
+		let mut eat = Action::new("Eat", Cost(1));
+		eat.push_effect(Effect::Sub(hunger, Value::Ref(satiety)));
+		let mut planner = Planner::new();
+		planner.push_action(eat);
+
+		let mut goal = Goal::new("Sated");
+		goal.push_condition(Condition::Le(hunger, Value::Int(0)));
+
+		let small = WorldState::new(2);
+		assert!(small.try_get(satiety).is_err());
+		assert!(
+			Effect::Set(satiety, Value::TRUE)
+				.try_apply(&mut small.clone())
+				.is_err()
+		);
+		assert!(matches!(
+			planner.plan(&small, &goal),
+			Err(PlannerError::IncompatibleState { fact, len: 2 }) if fact == satiety
+		));
+		assert!(planner.check_compatible(&WorldState::new(4)).is_ok());
+	}
+
+	#[test]
+	fn diff_patches_back() {
+		let hunger = FactId(0);
+		let food = FactId(1);
+		let before = WorldState::new(2).with_fact(hunger, Value::Int(5));
+		let after = before
+			.with_fact(hunger, Value::Ref(food))
+			.with_fact(FactId(2), Value::TRUE);
+
+		let changes = before.diff(&after);
+		assert_eq!(
+			format!("{changes:?}"),
+			"[FactChange { fact: FactId(0), old: Int(5), new: Ref(FactId(1)) }, \
+			 FactChange { fact: FactId(2), old: Error, new: Int(1) }]"
+		);
+		assert!(after.diff(&after).is_empty());
+
+		let mut patched = before.clone();
+		patched.apply_patch(&before.patch_to(&after));
+		assert!(patched.diff(&after).is_empty());
+
+		let undo: Vec<Effect> = changes[..1]
+			.iter()
+			.map(|change| change.reverted().to_effect())
+			.collect();
+		patched.apply_patch(&undo);
+		assert_eq!(format!("{:?}", patched.get(hunger)), "Int(5)");
+	}
+
+	#[test]
+	fn ref_cycles_resolve_to_error() {
+		let (a, b, c, d) = (FactId(0), FactId(1), FactId(2), FactId(3));
+		let chain = WorldState::new(4)
+			.with_fact(a, Value::Ref(b))
+			.with_fact(b, Value::Ref(c))
+			.with_fact(c, Value::Int(7));
+		assert_eq!(
+			format!("{:?}", Value::Ref(a).resolve_fully(&chain)),
+			"Int(7)"
+		);
+		assert!(chain.check_ref_cycles().is_ok());
+
+		let cyclic = chain
+			.with_fact(c, Value::Ref(d))
+			.with_fact(d, Value::Ref(b));
+		assert!(Value::Ref(a).resolve_fully(&cyclic).is_error());
+		assert_eq!(cyclic.find_ref_cycle(), Some(vec![b, c, d]));
+		assert_eq!(
+			cyclic.check_ref_cycles(),
+			Err(StateError::RefCycle(vec![b, c, d]))
+		);
+		let own = WorldState::new(1).with_fact(a, Value::Ref(a));
+		assert_eq!(own.find_ref_cycle(), Some(vec![a]));
+
+		// hashing, comparing and planning all terminate
+		assert_eq!(cyclic, cyclic.clone());
+		assert_eq!(hash_of(&cyclic), hash_of(&cyclic.clone()));
+		let mut fix = Action::new("Fix", Cost(1));
+		fix.push_effect(Effect::Set(d, Value::Int(1)));
+		let mut planner = Planner::new();
+		planner.push_action(fix);
+		let mut goal = Goal::new("Resolved");
+		goal.push_condition(Condition::Eq(a, Value::Int(1)));
+		assert_eq!(
+			planner.plan(&cyclic, &goal).unwrap().actions,
+			vec![ActionId(0)]
+		);
+
+		// revisiting a cyclic state is recognised, so an unreachable goal exhausts the search
+		let lamp = FactId(4);
+		let cyclic = cyclic.with_fact(lamp, Value::Int(0));
+		let mut planner = Planner::new();
+		for (name, lit) in [("On", 1), ("Off", 0)] {
+			let mut toggle = Action::new(name, Cost(1));
+			toggle.push_effect(Effect::Set(lamp, Value::Int(lit)));
+			planner.push_action(toggle);
+		}
+		let mut goal = Goal::new("Unreachable");
+		goal.push_condition(Condition::Eq(lamp, Value::Int(2)));
+		assert!(matches!(
+			planner.plan(&cyclic, &goal),
+			Err(PlannerError::NoPlanFound)
+		));
+
+		// the same goes for a layered state, with the cycle in its base and an error on top
+		let layered = WorldState::layered(Arc::new(cyclic)).with_fact(lamp, Value::Error);
+		assert_eq!(layered, layered.clone());
+		assert_eq!(layered, layered.flattened());
+		assert_eq!(hash_of(&layered), hash_of(&layered.flattened()));
+		assert!(matches!(
+			planner.plan(&layered, &goal),
+			Err(PlannerError::NoPlanFound)
+		));
+	}
+
+	#[test]
+	fn errors_are_unknown_in_every_comparison() {
+		let (hp, armor, missing) = (FactId(0), FactId(1), FactId(9));
+		let state = WorldState::new(2)
+			.with_fact(hp, Value::Int(3))
+			.with_fact(armor, Value::Error);
+		for value in [Value::Error, Value::Ref(armor), Value::Ref(missing)] {
+			for condition in [
+				Condition::Eq(hp, value),
+				Condition::Ne(hp, value),
+				Condition::Gt(hp, value),
+				Condition::Lt(hp, value),
+				Condition::Ge(hp, value),
+				Condition::Le(hp, value),
+				Condition::Le(armor, Value::Int(3)),
+				Condition::Gt(missing, Value::Int(3)),
+			] {
+				assert_eq!(condition.evaluate(&state), None, "{condition:?}");
+				assert!(!condition.is_satisfied(&state), "{condition:?}");
+			}
+		}
+		let unknown = Condition::Ne(armor, Value::Int(0));
+		let known = Condition::Gt(hp, Value::Int(5));
+		assert_eq!(evaluate_conditions(&[unknown, known], &state), Some(false));
+		assert_eq!(
+			evaluate_conditions(&[Condition::Eq(hp, Value::Int(3)), unknown], &state),
+			None
+		);
+
+		let mut wait = Action::new("Wait", Cost(1));
+		wait.push_precondition(unknown)
+			.push_effect(Effect::Set(hp, Value::Int(4)));
+		let mut planner = Planner::new();
+		planner.push_action(wait);
+		let mut goal = Goal::new("Healed");
+		goal.push_condition(Condition::Eq(hp, Value::Int(4)));
+		assert!(matches!(
+			planner.plan(&state, &goal),
+			Err(PlannerError::NoPlanFound)
+		));
+		planner.set_error_policy(ErrorPolicy::Fail);
+		assert!(matches!(
+			planner.plan(&state, &goal),
+			Err(PlannerError::ErroneousCondition(Condition::Ne(fact, _))) if fact == armor
+		));
+
+		// unknown in conditions, but a state holding an error is still equal to itself
+		assert!(!Value::Error.eq(&Value::Error, &state));
+		assert_eq!(state, state.clone());
+		let mut planner = Planner::new();
+		for (name, lit) in [("Hurt", 3), ("Heal", 5)] {
+			let mut action = Action::new(name, Cost(1));
+			action.push_effect(Effect::Set(hp, Value::Int(lit)));
+			planner.push_action(action);
+		}
+		assert!(matches!(
+			planner.plan(&state, &goal),
+			Err(PlannerError::NoPlanFound)
+		));
+	}
+
+	#[test]
+	fn search_is_traced() {
+		#[derive(Default)]
+		struct Log(Vec<String>);
+
+		impl PlanTracer for Log {
+			fn expand(&mut self, state: &WorldState, g_cost: Cost) {
+				self.0
+					.push(format!("expand {:?} g={}", state.get(FactId(0)), g_cost.0));
+			}
+
+			fn apply(&mut self, _: &WorldState, action: ActionId, _: &WorldState, g_cost: Cost) {
+				self.0.push(format!("apply {} g={}", action.0, g_cost.0));
+			}
+
+			fn block(&mut self, _: &WorldState, action: ActionId, failed: &[Condition]) {
+				self.0.push(format!("block {} {failed:?}", action.0));
+			}
+
+			fn skip(&mut self, _: &WorldState, action: ActionId) {
+				self.0.push(format!("skip {}", action.0));
+			}
+
+			fn reach_goal(&mut self, _: &WorldState, g_cost: Cost) {
+				self.0.push(format!("goal g={}", g_cost.0));
+			}
+		}
+
+		let door = FactId(0);
+		let mut open = Action::new("Open", Cost(2));
+		open.push_precondition(Condition::Eq(door, Value::FALSE))
+			.push_effect(Effect::Set(door, Value::TRUE));
+		let mut close = Action::new("Close", Cost(1));
+		close.push_effect(Effect::Set(door, Value::FALSE));
+		let mut planner = Planner::new();
+		planner.push_action(open);
+		planner.push_action(close);
+		let mut knock = Action::new("Knock", Cost(1));
+		knock
+			.push_precondition(Condition::Eq(door, Value::TRUE))
+			.push_effect(Effect::Set(door, Value::Int(2)));
+		planner.push_action(knock);
+		let mut goal = Goal::new("Open door");
+		goal.push_condition(Condition::Eq(door, Value::TRUE));
+
+		let mut log = Log::default();
+		let plan = planner
+			.plan_traced(
+				&WorldState::new(1),
+				&goal,
+				&PlanOptions::default(),
+				&mut log,
+			)
+			.unwrap();
+		assert_eq!(plan.actions, vec![ActionId(0)]);
+		assert_eq!(
+			log.0,
+			[
+				"expand Int(0) g=0",
+				"apply 0 g=2",
+				"skip 1",
+				"block 2 [Eq(FactId(0), Int(1))]",
+				"goal g=2",
+			]
+		);
+	}
+
+	#[test]
+	fn action_ids_are_stable() {
+		let door = FactId(0);
+		let mut open = Action::new("Open", Cost(3));
+		open.push_effect(Effect::Set(door, Value::TRUE));
+		let mut kick = Action::new("Kick", Cost(5));
+		kick.push_effect(Effect::Set(door, Value::TRUE));
+		let mut planner = Planner::new();
+		let wait = planner.push_action(Action::new("Wait", Cost(1)));
+		let open = planner.push_action(open);
+		let kick = planner.push_action(kick);
+		assert_eq!(planner.get_action_id("Kick"), Some(kick));
+		assert_eq!(planner.get_action_by_name("Open").unwrap().cost, Cost(3));
+
+		let mut goal = Goal::new("Open door");
+		goal.push_condition(Condition::Eq(door, Value::TRUE));
+		let start = WorldState::new(1);
+		let plan = planner.plan(&start, &goal).unwrap();
+		assert_eq!(plan.actions, vec![open]);
+
+		assert_eq!(planner.remove_action(wait).unwrap().name, "Wait");
+		assert!(planner.remove_action(wait).is_none());
+		assert_eq!(planner.get_plan_action(&plan, 0).unwrap().name, "Open");
+		assert!(planner.replay(&start, &plan).is_ok());
+
+		planner.get_action_mut(kick).unwrap().cost = Cost(1);
+		let renamed = Action::new("Unlock", Cost(1));
+		assert_eq!(planner.replace_action(open, renamed).unwrap().name, "Open");
+		assert!(
+			planner
+				.replace_action(wait, Action::new("Wait", Cost(1)))
+				.is_none()
+		);
+		let names: Vec<_> = planner
+			.actions()
+			.map(|(id, action)| (id, action.name.as_str()))
+			.collect();
+		assert_eq!(names, [(open, "Unlock"), (kick, "Kick")]);
+		assert_eq!(planner.num_actions(), 2);
+		planner.remove_action(open);
+		assert!(matches!(
+			planner.replay(&start, &plan),
+			Err(PlannerError::UnreachableState)
+		));
+	}
+
+	#[test]
+	fn plan_with_masks_and_cost_overrides() {
+		let distance = FactId(0);
+		let mut walk = Action::new("Walk", Cost(2));
+		walk.push_precondition(Condition::Gt(distance, Value::Int(0)))
+			.push_effect(Effect::Sub(distance, Value::Int(1)));
+		let mut sprint = Action::new("Sprint", Cost(3));
+		sprint
+			.push_precondition(Condition::Gt(distance, Value::Int(1)))
+			.push_effect(Effect::Sub(distance, Value::Int(2)));
+		let mut planner = Planner::new();
+		let walk = planner.push_action(walk);
+		let sprint = planner.push_action(sprint);
+		let mut goal = Goal::new("Arrive");
+		goal.push_condition(Condition::Eq(distance, Value::Int(0)));
+		let start = WorldState::new(1).with_fact(distance, Value::Int(4));
+
+		let healthy = planner.plan(&start, &goal).unwrap();
+		assert_eq!(healthy.actions, vec![sprint, sprint]);
+		assert_eq!(healthy.total_cost, Cost(6));
+
+		let wounded = PlanOptions::new().with_mask([walk].into_iter().collect());
+		let plan = planner.plan_with(&start, &goal, &wounded).unwrap();
+		assert_eq!(plan.actions, vec![walk; 4]);
+		assert_eq!(plan.total_cost, Cost(8));
+
+		let tired = PlanOptions::new().with_cost(sprint, Cost(5));
+		let plan = planner.plan_with(&start, &goal, &tired).unwrap();
+		assert_eq!(plan.actions, vec![walk; 4]);
+
+		// a negative override is used as 0, which search needs to find the cheapest plan
+		let eager = PlanOptions::new().with_cost(walk, Cost(-5));
+		let walk_action = planner.get_action(walk).unwrap();
+		assert_eq!(eager.cost(walk, walk_action, &start), Cost(0));
+		let plan = planner.plan_with(&start, &goal, &eager).unwrap();
+		assert_eq!(plan.actions, vec![walk; 4]);
+		assert_eq!(plan.total_cost, Cost(0));
+
+		let mut mask = ActionMask::new();
+		mask.allow(walk).allow(sprint).deny(walk);
+		assert!(mask.allows(sprint) && !mask.allows(walk) && !mask.allows(ActionId(100)));
+	}
+
+	#[test]
+	fn action_tags() {
+		let distance = FactId(0);
+		let afraid = FactId(1);
+		let mut walk = Action::new("Walk", Cost(2));
+		walk.push_precondition(Condition::Gt(distance, Value::Int(0)))
+			.push_effect(Effect::Sub(distance, Value::Int(1)))
+			.push_tag("movement")
+			.push_tag("stealth");
+		let mut charge = Action::new("Charge", Cost(3));
+		charge
+			.push_precondition(Condition::Gt(distance, Value::Int(1)))
+			.push_effect(Effect::Sub(distance, Value::Int(2)))
+			.push_tag("movement")
+			.push_tag("combat");
+		let mut planner = Planner::new();
+		let walk = planner.push_action(walk);
+		let charge = planner.push_action(charge);
+		let mut goal = Goal::new("Arrive");
+		goal.push_condition(Condition::Eq(distance, Value::Int(0)));
+		let start = WorldState::new(2)
+			.with_fact(distance, Value::Int(4))
+			.with_fact(afraid, Value::FALSE);
+
+		let combat: Vec<_> = planner
+			.actions_with_tag("combat")
+			.map(|(id, _)| id)
+			.collect();
+		assert_eq!(combat, vec![charge]);
+		let mask = planner.tag_mask(&["stealth"]);
+		assert!(mask.allows(walk) && !mask.allows(charge));
+		assert_eq!(
+			planner.plan(&start, &goal).unwrap().actions,
+			vec![charge, charge]
+		);
+
+		let options = PlanOptions::new().with_cost_modifier(
+			CostModifier::new("combat", Cost(5)).when(Condition::Eq(afraid, Value::TRUE)),
+		);
+		let plan = planner.plan_with(&start, &goal, &options).unwrap();
+		assert_eq!(plan.actions, vec![charge, charge]);
+		let scared = start.clone().with_fact(afraid, Value::TRUE);
+		let plan = planner.plan_with(&scared, &goal, &options).unwrap();
+		assert_eq!(plan.actions, vec![walk; 4]);
+		assert_eq!(plan.total_cost, Cost(8));
+
+		// a discount can make an action free, but not negative
+		let options =
+			PlanOptions::new().with_cost_modifier(CostModifier::new("movement", Cost(-10)));
+		let walk_action = planner.get_action(walk).unwrap();
+		assert_eq!(options.cost(walk, walk_action, &start), Cost(0));
+		assert_eq!(
+			planner
+				.plan_with(&start, &goal, &options)
+				.unwrap()
+				.total_cost,
+			Cost(0)
+		);
+
+		// explain only considers the actions plan would use
+		let odd = start.clone().with_fact(distance, Value::Int(3));
+		let masked = PlanOptions::new().with_mask(planner.tag_mask(&["combat"]));
+		assert!(planner.plan_with(&odd, &goal, &masked).is_err());
+		let explanation = planner.explain_with(&odd, &goal, &masked).unwrap();
+		assert!(!explanation.goal_reached);
+		assert_eq!(explanation.never_applicable.len(), 0);
+		let mut fight = goal.clone();
+		fight.push_allowed_tag("combat");
+		assert!(planner.plan(&odd, &fight).is_err());
+		assert!(!planner.explain(&odd, &fight).unwrap().goal_reached);
+
+		goal.push_allowed_tag("stealth");
+		assert_eq!(planner.plan(&start, &goal).unwrap().actions, vec![walk; 4]);
+		assert!(planner.explain(&start, &goal).unwrap().goal_reached);
+	}
+
+	#[test]
+	fn plan_multi_goal_modes() {
+		let fed = FactId(0);
+		let rested = FactId(1);
+		let mut eat = Action::new("Eat", Cost(2));
+		eat.push_effect(Effect::Set(fed, Value::TRUE));
+		let mut sleep = Action::new("Sleep", Cost(3));
+		sleep
+			.push_effect(Effect::Set(rested, Value::TRUE))
+			.push_effect(Effect::Set(fed, Value::FALSE));
+		let mut planner = Planner::new();
+		let eat = planner.push_action(eat);
+		let sleep = planner.push_action(sleep);
+		let mut eat_goal = Goal::new("Eat");
+		eat_goal.push_condition(Condition::Eq(fed, Value::TRUE));
+		let mut sleep_goal = Goal::new("Sleep");
+		sleep_goal.push_condition(Condition::Eq(rested, Value::TRUE));
+		let start = WorldState::new(2)
+			.with_fact(fed, Value::FALSE)
+			.with_fact(rested, Value::FALSE);
+		let options = PlanOptions::new();
+
+		let mut multi = MultiGoal::new(GoalMode::All);
+		multi
+			.push_goal(eat_goal.clone(), Cost(0))
+			.push_goal(sleep_goal.clone(), Cost(0));
+		let all = planner.plan_multi(&start, &multi, &options).unwrap();
+		assert_eq!(all.plan.actions, vec![sleep, eat]);
+		assert_eq!(all.plan.total_cost, Cost(5));
+		assert_eq!(all.satisfied, vec![Some(2), Some(1)]);
+		assert_eq!(all.goals_satisfied_at(1).collect::<Vec<_>>(), vec![1]);
+
+		multi.mode = GoalMode::Sequential;
+		let sequential = planner.plan_multi(&start, &multi, &options).unwrap();
+		assert_eq!(sequential.plan.actions, vec![eat, sleep]);
+		assert_eq!(sequential.satisfied, vec![Some(1), Some(2)]);
+
+		multi.mode = GoalMode::Any;
+		let any = planner.plan_multi(&start, &multi, &options).unwrap();
+		assert_eq!(any.plan.actions, vec![eat]);
+		assert_eq!(any.satisfied, vec![Some(1), None]);
+		multi.goals[1].weight = Cost(5);
+		let any = planner.plan_multi(&start, &multi, &options).unwrap();
+		assert_eq!(any.plan.actions, vec![sleep]);
+		assert_eq!(any.satisfied, vec![None, Some(1)]);
+		// extreme weights don't overflow the score
+		multi.goals[0].weight = Cost(i32::MIN);
+		multi.goals[1].weight = Cost(i32::MAX);
+		let any = planner.plan_multi(&start, &multi, &options).unwrap();
+		assert_eq!(any.plan.actions, vec![sleep]);
+	}
+
+	#[test]
+	fn htn_decomposition_backtracks() {
+		let has_axe = FactId(0);
+		let has_wood = FactId(1);
+		let money = FactId(2);
+		let mut get_axe = Action::new("Get Axe", Cost(1));
+		get_axe.push_effect(Effect::Set(has_axe, Value::TRUE));
+		let mut chop = Action::new("Chop Wood", Cost(2));
+		chop.push_precondition(Condition::Eq(has_axe, Value::TRUE))
+			.push_effect(Effect::Set(has_wood, Value::TRUE));
+		let mut buy = Action::new("Buy Wood", Cost(1));
+		buy.push_precondition(Condition::Gt(money, Value::Int(0)))
+			.push_effect(Effect::Set(has_wood, Value::TRUE))
+			.push_effect(Effect::Sub(money, Value::Int(1)));
+		let mut planner = Planner::new();
+		let get_axe = planner.push_action(get_axe);
+		let chop = planner.push_action(chop);
+		let buy = planner.push_action(buy);
+
+		let mut htn = HtnPlanner::new();
+		let get_wood = htn.push_task(CompoundTask::new("Get Wood"));
+		let mut by_buying = Method::new("Buy");
+		by_buying.push_subtask(Task::Primitive(buy));
+		let mut by_chopping = Method::new("Chop");
+		by_chopping
+			.push_precondition(Condition::Eq(has_axe, Value::TRUE))
+			.push_subtask(Task::Primitive(chop));
+		let mut axe_first = Method::new("Fetch Axe");
+		axe_first
+			.push_precondition(Condition::Eq(has_axe, Value::FALSE))
+			.push_subtask(Task::Primitive(get_axe))
+			.push_subtask(Task::Compound(get_wood));
+		htn.get_task_mut(get_wood)
+			.unwrap()
+			.push_method(by_buying)
+			.push_method(by_chopping)
+			.push_method(axe_first);
+		assert_eq!(htn.get_task_id("Get Wood"), Some(get_wood));
+
+		let start = WorldState::new(3)
+			.with_fact(has_axe, Value::FALSE)
+			.with_fact(has_wood, Value::FALSE)
+			.with_fact(money, Value::Int(0));
+		let plan = htn
+			.plan(&planner, &start, Task::Compound(get_wood))
+			.unwrap();
+		assert_eq!(plan.actions, vec![get_axe, chop]);
+		assert_eq!(plan.total_cost, Cost(3));
+		let states = planner.replay(&start, &plan).unwrap();
+		assert!(
+			states
+				.last()
+				.unwrap()
+				.get(has_wood)
+				.eq(&Value::TRUE, &start)
+		);
+
+		let rich = start.clone().with_fact(money, Value::Int(1));
+		let plan = htn.plan(&planner, &rich, Task::Compound(get_wood)).unwrap();
+		assert_eq!(plan.actions, vec![buy]);
+
+		assert!(matches!(
+			htn.plan(&planner, &start, Task::Compound(TaskId(9))),
+			Err(HtnError::UnknownTask(TaskId(9)))
+		));
+		let mut forever = Method::new("Forever");
+		forever.push_subtask(Task::Compound(TaskId(1)));
+		let mut stall = CompoundTask::new("Stall");
+		stall.push_method(forever);
+		let stall = htn.push_task(stall);
+		assert!(matches!(
+			htn.plan(&planner, &start, Task::Compound(stall)),
+			Err(HtnError::StepLimit(_))
+		));
+		let mut chop_only = Method::new("Chop");
+		chop_only.push_subtask(Task::Primitive(chop));
+		htn.get_task_mut(stall).unwrap().methods = vec![chop_only];
+		assert!(matches!(
+			htn.plan(&planner, &start, Task::Compound(stall)),
+			Err(HtnError::NoDecomposition)
+		));
+	}
+
+	#[test]
+	fn coordinated_agents_share_resources() {
+		let axe_available = FactId(0);
+		let mut planner = Planner::new();
+		let mut steps = Vec::new();
+		// per woodcutter: has_axe, has_wood
+		for (has_axe, has_wood) in [(FactId(1), FactId(2)), (FactId(3), FactId(4))] {
+			let mut take = Action::new("Take Axe", Cost(1));
+			take.push_precondition(Condition::Eq(axe_available, Value::TRUE))
+				.push_effect(Effect::Set(axe_available, Value::FALSE))
+				.push_effect(Effect::Set(has_axe, Value::TRUE));
+			let mut chop = Action::new("Chop Wood", Cost(1));
+			chop.push_precondition(Condition::Eq(has_axe, Value::TRUE))
+				.push_effect(Effect::Set(has_wood, Value::TRUE));
+			let mut buy = Action::new("Buy Wood", Cost(5));
+			buy.push_effect(Effect::Set(has_wood, Value::TRUE));
+			let mut goal = Goal::new("Get Wood");
+			goal.push_condition(Condition::Eq(has_wood, Value::TRUE));
+			steps.push((
+				planner.push_action(take),
+				planner.push_action(chop),
+				planner.push_action(buy),
+				goal,
+			));
+		}
+		let mut start = WorldState::new(5).with_fact(axe_available, Value::TRUE);
+		for fact in 1..5 {
+			start.push_fact(FactId(fact), Value::FALSE);
+		}
+		let (first, second) = (AgentId(1), AgentId(2));
+		let options = PlanOptions::new();
+		let mut coordinator = Coordinator::new();
+		coordinator.push_resource(axe_available);
+
+		let (take, chop, _, goal) = &steps[0];
+		let plan = coordinator
+			.plan(&planner, first, &start, goal, &options)
+			.unwrap();
+		assert_eq!(plan.actions, vec![*take, *chop]);
+		assert_eq!(coordinator.reserved_by(axe_available), Some(first));
+
+		let (take, chop, buy, goal) = &steps[1];
+		let plan = coordinator
+			.plan(&planner, second, &start, goal, &options)
+			.unwrap();
+		assert_eq!(plan.actions, vec![*buy]);
+		assert_eq!(
+			coordinator.reserve(second, axe_available),
+			Err(ReservationError::Reserved {
+				fact: axe_available,
+				by: first
+			})
+		);
+		assert!(coordinator.conflicts(&planner).is_empty());
+
+		let greedy = Plan {
+			actions: vec![*take, *chop],
+			total_cost: Cost(2),
+		};
+		coordinator.record_plan(second, greedy);
+		assert_eq!(
+			coordinator.conflicts(&planner),
+			vec![Conflict {
+				fact: axe_available,
+				writer: (first, 0),
+				other: (second, 0),
+			}]
+		);
+
+		coordinator.finish(first);
+		assert_eq!(coordinator.reserved_by(axe_available), None);
+		let plan = coordinator
+			.plan(&planner, second, &start, goal, &options)
+			.unwrap();
+		assert_eq!(plan.actions, vec![*take, *chop]);
+		assert_eq!(coordinator.get_plan(second).unwrap().actions, plan.actions);
+	}
+
+	#[test]
+	fn layered_states_read_through_to_a_shared_base() {
+		let alarm = FactId(0);
+		let night = FactId(1);
+		let hidden = FactId(2);
+		let shared = Arc::new(
+			WorldState::new(2)
+				.with_fact(alarm, Value::TRUE)
+				.with_fact(night, Value::FALSE),
+		);
+		let mut agent = WorldState::layered(Arc::clone(&shared));
+		agent.push_fact(hidden, Value::FALSE);
+		assert_eq!(agent.len(), 3);
+		assert_eq!(agent.overlay().len(), 1);
+		assert!(Condition::Eq(alarm, Value::TRUE).is_satisfied(&agent));
+
+		let mut hide = Action::new("Hide", Cost(1));
+		hide.push_precondition(Condition::Eq(alarm, Value::TRUE))
+			.push_effect(Effect::Set(hidden, Value::TRUE));
+		let mut planner = Planner::new();
+		let hide = planner.push_action(hide);
+		let mut goal = Goal::new("Stay Safe");
+		goal.push_condition(Condition::Eq(hidden, Value::TRUE));
+		let plan = planner.plan(&agent, &goal).unwrap();
+		assert_eq!(plan.actions, vec![hide]);
+
+		let after = planner.replay(&agent, &plan).unwrap().pop().unwrap();
+		assert!(Arc::ptr_eq(after.base().unwrap(), &shared));
+		assert_eq!(after, after.flattened());
+		assert_eq!(agent.diff(&after).len(), 1);
+		assert_eq!(hash_of(&after), hash_of(&after.flattened()));
+
+		agent.rebase(Arc::new(shared.with_fact(alarm, Value::FALSE)));
+		assert!(planner.plan(&agent, &goal).is_err());
+		assert!(agent.get(hidden).eq(&Value::FALSE, &agent));
+		assert!(agent.try_set(FactId(3), Value::TRUE).is_err());
+	}
+
+	#[test]
+	fn packed_states_keep_every_value() {
+		for len in [5, 40] {
+			let mut state = WorldState::new(len);
+			let big = Value::Int(i64::from(i32::MAX) + 1);
+			let values = [
+				big,
+				Value::Int(i64::from(i32::MIN)),
+				Value::Ref(FactId(0)),
+				Value::Error,
+			];
+			for (index, value) in values.into_iter().enumerate() {
+				state.set(FactId(u16::try_from(index + 1).unwrap()), value);
+			}
+			let before = state.clone();
+			assert!(state.get(FactId(1)).eq(&big, &state));
+			assert!(
+				state
+					.get(FactId(2))
+					.eq(&Value::Int(i64::from(i32::MIN)), &state)
+			);
+			assert!(matches!(state.get(FactId(3)), Value::Ref(FactId(0))));
+			assert!(matches!(state.get(FactId(4)), Value::Error));
+			assert_eq!(state, state.flattened());
+			assert_eq!(
+				hash_of(&state),
+				hash_of(&WorldState::layered(Arc::new(state.clone())))
+			);
+
+			// writing to a clone leaves the original alone
+			for index in 1..=4 {
+				state.set(FactId(index), Value::Int(7));
+			}
+			assert_ne!(state, before);
+			assert!(before.get(FactId(1)).eq(&big, &before));
+			let plain = WorldState::new(len).with_effects(
+				&(1..=4)
+					.map(|index| Effect::Set(FactId(index), Value::Int(7)))
+					.collect::<Vec<_>>(),
+			);
+			assert_eq!(state, plain);
+			assert_eq!(hash_of(&state), hash_of(&plain));
+		}
+	}
 }
@@ -0,0 +1,210 @@
+use crate::condition::conditions_met;
+use crate::cost::Cost;
+use crate::errors::PlannerError;
+use crate::goal::Goal;
+use crate::options::PlanOptions;
+use crate::plan::Plan;
+use crate::planner::Planner;
+use crate::world::WorldState;
+
+/// How the goals of a `MultiGoal` combine.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum GoalMode {
+	/// Reach a state that satisfies every goal at once, as cheaply as possible.
+	#[default]
+	All,
+	/// Satisfy one goal: the one with the lowest plan cost minus its weight.
+	Any,
+	/// Satisfy the goals one after the other, in order. Each goal is planned for separately from where
+	/// the previous one left off, so later goals may undo earlier ones.
+	Sequential,
+}
+
+/// Several goals to plan for at once with `Planner::plan_multi`, e.g. "eat and sleep".
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MultiGoal {
+	pub mode: GoalMode,
+	pub goals: Vec<WeightedGoal>,
+}
+
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct WeightedGoal {
+	pub goal: Goal,
+	/// How much satisfying the goal is worth, in cost units. Only used by `GoalMode::Any`.
+	#[cfg_attr(feature = "serde", serde(default))]
+	pub weight: Cost,
+}
+
+impl MultiGoal {
+	#[must_use]
+	pub fn new(mode: GoalMode) -> Self {
+		Self {
+			mode,
+			goals: Vec::new(),
+		}
+	}
+
+	pub fn push_goal(&mut self, goal: Goal, weight: Cost) -> &mut Self {
+		self.goals.push(WeightedGoal { goal, weight });
+		self
+	}
+}
+
+/// Returned by `Planner::plan_multi`: one plan, and where in it each goal is satisfied.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MultiPlan {
+	pub plan: Plan,
+	/// Indexed like `MultiGoal::goals`: the number of steps after which the goal is satisfied
+	/// (`Some(0)` if it already is at the start), or `None` if the plan doesn't satisfy it.
+	/// For `All` and `Any` this is the step from which the goal stays satisfied until the end.
+	pub satisfied: Vec<Option<usize>>,
+}
+
+impl MultiPlan {
+	/// The goals that become satisfied after `step` steps of the plan.
+	pub fn goals_satisfied_at(&self, step: usize) -> impl Iterator<Item = usize> + '_ {
+		self.satisfied
+			.iter()
+			.enumerate()
+			.filter(move |(_, satisfied)| **satisfied == Some(step))
+			.map(|(goal, _)| goal)
+	}
+}
+
+impl Planner {
+	/// Plan for several goals at once, see `GoalMode`. Goal weights only matter for `GoalMode::Any`;
+	/// `All` and `Sequential` ignore them, and `Sequential` follows the order of `MultiGoal::goals`.
+	///
+	/// # Errors
+	/// - `PlannerError::NoPlanFound`: if the goals can't be satisfied, or `GoalMode::Any` has no goals
+	/// - anything `plan_with` returns for one of the goals
+	pub fn plan_multi(
+		&self,
+		start: &WorldState,
+		multi: &MultiGoal,
+		options: &PlanOptions,
+	) -> Result<MultiPlan, PlannerError> {
+		match multi.mode {
+			GoalMode::All => self.plan_all(start, multi, options),
+			GoalMode::Any => self.plan_any(start, multi, options),
+			GoalMode::Sequential => self.plan_sequential(start, multi, options),
+		}
+	}
+
+	fn plan_all(
+		&self,
+		start: &WorldState,
+		multi: &MultiGoal,
+		options: &PlanOptions,
+	) -> Result<MultiPlan, PlannerError> {
+		// one goal with every condition, restricted to the actions every goal allows
+		let mut combined = Goal::new("All");
+		for weighted in &multi.goals {
+			combined
+				.conditions
+				.extend_from_slice(&weighted.goal.conditions);
+		}
+		let mut options = options.clone();
+		options.mask = Some(
+			self.actions()
+				.filter(|(id, action)| {
+					options.allows(*id)
+						&& multi
+							.goals
+							.iter()
+							.all(|weighted| weighted.goal.allows(action))
+				})
+				.map(|(id, _)| id)
+				.collect(),
+		);
+
+		let plan = self.plan_with(start, &combined, &options)?;
+		let states = self.states_of(start, &plan)?;
+		let satisfied = multi
+			.goals
+			.iter()
+			.map(|weighted| satisfied_until_end(&weighted.goal, &states))
+			.collect();
+		Ok(MultiPlan { plan, satisfied })
+	}
+
+	fn plan_any(
+		&self,
+		start: &WorldState,
+		multi: &MultiGoal,
+		options: &PlanOptions,
+	) -> Result<MultiPlan, PlannerError> {
+		// in i64, so large weights can't overflow
+		let score = |(index, plan): &(usize, Plan)| {
+			i64::from(plan.total_cost.0) - i64::from(multi.goals[*index].weight.0)
+		};
+		let mut best: Option<(usize, Plan)> = None;
+		for (index, weighted) in multi.goals.iter().enumerate() {
+			let plan = match self.plan_with(start, &weighted.goal, options) {
+				Ok(plan) => plan,
+				Err(PlannerError::NoPlanFound) => continue,
+				Err(err) => return Err(err),
+			};
+			let candidate = (index, plan);
+			if best
+				.as_ref()
+				.is_none_or(|best| score(&candidate) < score(best))
+			{
+				best = Some(candidate);
+			}
+		}
+
+		let (index, plan) = best.ok_or(PlannerError::NoPlanFound)?;
+		let states = self.states_of(start, &plan)?;
+		let mut satisfied = vec![None; multi.goals.len()];
+		satisfied[index] = satisfied_until_end(&multi.goals[index].goal, &states);
+		Ok(MultiPlan { plan, satisfied })
+	}
+
+	fn plan_sequential(
+		&self,
+		start: &WorldState,
+		multi: &MultiGoal,
+		options: &PlanOptions,
+	) -> Result<MultiPlan, PlannerError> {
+		let mut plan = Plan {
+			actions: Vec::new(),
+			total_cost: Cost(0),
+		};
+		let mut satisfied = Vec::with_capacity(multi.goals.len());
+		let mut state = start.clone();
+		for weighted in &multi.goals {
+			let part = self.plan_with(&state, &weighted.goal, options)?;
+			if let Some(last) = self.replay(&state, &part)?.pop() {
+				state = last;
+			}
+			plan.actions.extend_from_slice(&part.actions);
+			plan.total_cost += part.total_cost;
+			satisfied.push(Some(plan.actions.len()));
+		}
+		Ok(MultiPlan { plan, satisfied })
+	}
+
+	/// `start` followed by the state after each step of `plan`.
+	fn states_of(&self, start: &WorldState, plan: &Plan) -> Result<Vec<WorldState>, PlannerError> {
+		let mut states = vec![start.clone()];
+		states.extend(self.replay(start, plan)?);
+		Ok(states)
+	}
+}
+
+/// The first index from which `goal` holds in every one of `states`.
+fn satisfied_until_end(goal: &Goal, states: &[WorldState]) -> Option<usize> {
+	let unsatisfied = states
+		.iter()
+		.rposition(|state| !conditions_met(&goal.conditions, state));
+	match unsatisfied {
+		None => Some(0),
+		Some(last) if last + 1 < states.len() => Some(last + 1),
+		Some(_) => None,
+	}
+}
@@ -0,0 +1,144 @@
+use std::collections::HashMap;
+
+use crate::action::{Action, ActionId};
+use crate::condition::{Condition, conditions_met};
+use crate::cost::Cost;
+use crate::world::WorldState;
+
+/// A set of actions an agent may use, as a bitset over `ActionId`s.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ActionMask {
+	bits: Vec<u64>,
+}
+
+impl ActionMask {
+	/// A mask that allows no actions.
+	#[must_use]
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	pub fn allow(&mut self, id: ActionId) -> &mut Self {
+		let (word, bit) = (id.index() / 64, id.index() % 64);
+		if word >= self.bits.len() {
+			self.bits.resize(word + 1, 0);
+		}
+		self.bits[word] |= 1 << bit;
+		self
+	}
+
+	pub fn deny(&mut self, id: ActionId) -> &mut Self {
+		let (word, bit) = (id.index() / 64, id.index() % 64);
+		if let Some(bits) = self.bits.get_mut(word) {
+			*bits &= !(1 << bit);
+		}
+		self
+	}
+
+	#[must_use]
+	pub fn allows(&self, id: ActionId) -> bool {
+		let (word, bit) = (id.index() / 64, id.index() % 64);
+		self.bits
+			.get(word)
+			.is_some_and(|bits| bits & (1 << bit) != 0)
+	}
+}
+
+impl FromIterator<ActionId> for ActionMask {
+	fn from_iter<T: IntoIterator<Item = ActionId>>(iter: T) -> Self {
+		let mut mask = Self::new();
+		for id in iter {
+			mask.allow(id);
+		}
+		mask
+	}
+}
+
+/// Per-agent adjustments for `Planner::plan_with`, so one shared action library can serve agents
+/// with different abilities.
+#[derive(Clone, Debug, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PlanOptions {
+	/// If set, only these actions are considered.
+	pub mask: Option<ActionMask>,
+	/// Costs to use instead of `Action::cost`. Negative costs are used as 0.
+	pub cost_overrides: HashMap<ActionId, Cost>,
+	/// Applied on top of the (possibly overridden) cost.
+	pub cost_modifiers: Vec<CostModifier>,
+}
+
+/// Adds `delta` to the cost of every action tagged `tag`, in states that meet `conditions`,
+/// e.g. "combat actions cost 5 more while afraid". A negative `delta` makes actions cheaper, but never
+/// below 0, which would break the planner's search for the cheapest plan.
+#[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CostModifier {
+	pub tag: String,
+	#[cfg_attr(feature = "serde", serde(default))]
+	pub conditions: Vec<Condition>,
+	pub delta: Cost,
+}
+
+impl CostModifier {
+	pub fn new(tag: impl Into<String>, delta: Cost) -> Self {
+		Self {
+			tag: tag.into(),
+			conditions: Vec::new(),
+			delta,
+		}
+	}
+
+	#[must_use]
+	pub fn when(mut self, condition: Condition) -> Self {
+		self.conditions.push(condition);
+		self
+	}
+}
+
+impl PlanOptions {
+	#[must_use]
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	#[must_use]
+	pub fn with_mask(mut self, mask: ActionMask) -> Self {
+		self.mask = Some(mask);
+		self
+	}
+
+	#[must_use]
+	pub fn with_cost(mut self, id: ActionId, cost: Cost) -> Self {
+		self.cost_overrides.insert(id, cost);
+		self
+	}
+
+	#[must_use]
+	pub fn with_cost_modifier(mut self, modifier: CostModifier) -> Self {
+		self.cost_modifiers.push(modifier);
+		self
+	}
+
+	/// Whether the mask (if any) allows `id`.
+	#[must_use]
+	pub fn allows(&self, id: ActionId) -> bool {
+		self.mask.as_ref().is_none_or(|mask| mask.allows(id))
+	}
+
+	/// The cost of applying `action` to `state` for this agent. Overrides and modifiers can't take it
+	/// below 0.
+	#[must_use]
+	pub fn cost(&self, id: ActionId, action: &Action, state: &WorldState) -> Cost {
+		let overridden = self.cost_overrides.get(&id).copied();
+		let mut cost = overridden.unwrap_or(action.cost);
+		let mut modified = overridden.is_some();
+		for modifier in &self.cost_modifiers {
+			if action.has_tag(&modifier.tag) && conditions_met(&modifier.conditions, state) {
+				cost = Cost(cost.0.saturating_add(modifier.delta.0));
+				modified = true;
+			}
+		}
+		if modified { Cost(cost.0.max(0)) } else { cost }
+	}
+}
@@ -1,17 +1,38 @@
+use core::cell::{Cell, RefCell};
+
 use pathfinding::prelude::astar;
 
-use crate::action::Action;
-use crate::condition::conditions_met;
+use crate::action::{Action, ActionId};
+use crate::condition::{Condition, conditions_met, evaluate_conditions};
 use crate::cost::Cost;
 use crate::errors::PlannerError;
+use crate::fact::{FactId, FactRange, FactRemap};
 use crate::goal::Goal;
+use crate::options::{ActionMask, PlanOptions};
 use crate::plan::Plan;
+use crate::trace::{PlanTracer, Trace};
 use crate::world::WorldState;
 
 #[derive(Clone, Debug)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Planner {
-	actions: Vec<Action>,
+	actions: Vec<Option<Action>>, // indexed by ActionId, `None` once removed
+	#[cfg_attr(feature = "serde", serde(default))]
+	pub(crate) ranges: Vec<FactRange>, // indexed by FactId
+	#[cfg_attr(feature = "serde", serde(default))]
+	error_policy: ErrorPolicy,
+}
+
+/// What `Planner::plan` does with a precondition or goal condition whose result is unknown because it
+/// compares `Value::Error` (see `Condition::evaluate`).
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ErrorPolicy {
+	/// Treat the condition as not satisfied.
+	#[default]
+	Unsatisfied,
+	/// Stop planning with `PlannerError::ErroneousCondition`.
+	Fail,
 }
 
 impl Planner {
@@ -19,73 +40,335 @@ impl Planner {
 	pub fn new() -> Self {
 		Self {
 			actions: Vec::new(),
+			ranges: Vec::new(),
+			error_policy: ErrorPolicy::default(),
 		}
 	}
 
-	pub fn push_action(&mut self, action: Action) {
-		self.actions.push(action);
+	pub fn push_action(&mut self, action: Action) -> ActionId {
+		self.actions.push(Some(action));
+		ActionId::from_index(self.actions.len() - 1)
+	}
+
+	#[must_use]
+	pub fn get_action(&self, id: ActionId) -> Option<&Action> {
+		self.actions.get(id.index())?.as_ref()
+	}
+
+	#[must_use]
+	pub fn get_action_mut(&mut self, id: ActionId) -> Option<&mut Action> {
+		self.actions.get_mut(id.index())?.as_mut()
+	}
+
+	/// The first action named `name`, see `get_action_id`.
+	#[must_use]
+	pub fn get_action_by_name(&self, name: &str) -> Option<&Action> {
+		self.get_action(self.get_action_id(name)?)
+	}
+
+	#[must_use]
+	pub fn get_action_id(&self, name: &str) -> Option<ActionId> {
+		self.actions()
+			.find(|(_, action)| action.name == name)
+			.map(|(id, _)| id)
+	}
+
+	/// Every action, in the order they were pushed.
+	pub fn actions(&self) -> impl Iterator<Item = (ActionId, &Action)> {
+		self.actions
+			.iter()
+			.enumerate()
+			.filter_map(|(index, action)| Some((ActionId::from_index(index), action.as_ref()?)))
+	}
+
+	/// Every action tagged `tag`.
+	pub fn actions_with_tag<'a>(
+		&'a self,
+		tag: &'a str,
+	) -> impl Iterator<Item = (ActionId, &'a Action)> + 'a {
+		self.actions()
+			.filter(move |(_, action)| action.has_tag(tag))
+	}
+
+	/// A mask allowing every action that has at least one of `tags`, for `PlanOptions::with_mask`.
+	#[must_use]
+	pub fn tag_mask(&self, tags: &[&str]) -> ActionMask {
+		self.actions()
+			.filter(|(_, action)| tags.iter().any(|tag| action.has_tag(tag)))
+			.map(|(id, _)| id)
+			.collect()
+	}
+
+	#[must_use]
+	pub fn num_actions(&self) -> usize {
+		self.actions.iter().flatten().count()
+	}
+
+	/// Remove an action; its id is not reused, so plans containing it can no longer be replayed.
+	pub fn remove_action(&mut self, id: ActionId) -> Option<Action> {
+		self.actions.get_mut(id.index())?.take()
+	}
+
+	/// Replace an action, keeping its id, and return the old one. If there is no action with `id`,
+	/// nothing changes and `None` is returned.
+	pub fn replace_action(&mut self, id: ActionId, action: Action) -> Option<Action> {
+		self.actions
+			.get_mut(id.index())?
+			.as_mut()
+			.map(|old| core::mem::replace(old, action))
+	}
+
+	/// Clamp every fact written by an action's effects to its range (indexed by `FactId`), see `FactMap::get_fact_ranges`.
+	pub fn set_fact_ranges(&mut self, ranges: Vec<FactRange>) {
+		self.ranges = ranges;
+	}
+
+	#[must_use]
+	pub fn get_fact_ranges(&self) -> &[FactRange] {
+		&self.ranges
+	}
+
+	pub fn set_error_policy(&mut self, error_policy: ErrorPolicy) {
+		self.error_policy = error_policy;
+	}
+
+	#[must_use]
+	pub fn get_error_policy(&self) -> ErrorPolicy {
+		self.error_policy
+	}
+
+	/// Check that every fact referenced by the actions fits in `state`, so planning from it can't index out of range.
+	///
+	/// # Errors
+	/// - `PlannerError::IncompatibleState`: for the first referenced fact that is not below `state.len()`
+	pub fn check_compatible(&self, state: &WorldState) -> Result<(), PlannerError> {
+		check_facts(
+			self.actions()
+				.flat_map(|(_, action)| action.referenced_facts()),
+			state,
+		)
+	}
+
+	/// `check_compatible` for a goal's conditions.
+	///
+	/// # Errors
+	/// - `PlannerError::IncompatibleState`: for the first referenced fact that is not below `state.len()`
+	pub fn check_goal_compatible(
+		&self,
+		state: &WorldState,
+		goal: &Goal,
+	) -> Result<(), PlannerError> {
+		check_facts(goal.referenced_facts(), state)
 	}
 
 	/// # Errors
 	/// - `PlannerError.NoPlanFound`: If no plan is found
 	/// - `PlannerError.UnreachableState`: If the planner produced an unreachable state between steps
+	/// - `PlannerError.IncompatibleState`: If an action or the goal references a fact `start` doesn't have (see `check_compatible`)
+	/// - `PlannerError.ErroneousCondition`: If the error policy is `ErrorPolicy::Fail` and a condition compared an error
 	pub fn plan(&self, start: &WorldState, goal: &Goal) -> Result<Plan, PlannerError> {
-		// run A* / Dijkstra
+		self.search(start, goal, &PlanOptions::default(), None)
+	}
+
+	/// `plan` for one agent: only with the actions `options` allows, and with its cost overrides.
+	/// `Plan::total_cost` uses the overridden costs.
+	///
+	/// # Errors
+	/// See `plan`.
+	pub fn plan_with(
+		&self,
+		start: &WorldState,
+		goal: &Goal,
+		options: &PlanOptions,
+	) -> Result<Plan, PlannerError> {
+		self.search(start, goal, options, None)
+	}
+
+	/// `plan_with`, reporting every step of the search to `tracer`.
+	///
+	/// # Errors
+	/// See `plan`.
+	pub fn plan_traced(
+		&self,
+		start: &WorldState,
+		goal: &Goal,
+		options: &PlanOptions,
+		tracer: &mut dyn PlanTracer,
+	) -> Result<Plan, PlannerError> {
+		let trace = RefCell::new(Trace::new(tracer, start));
+		self.search(start, goal, options, Some(&trace))
+	}
+
+	fn search(
+		&self,
+		start: &WorldState,
+		goal: &Goal,
+		options: &PlanOptions,
+		trace: Option<&RefCell<Trace>>,
+	) -> Result<Plan, PlannerError> {
+		self.check_compatible(start)?;
+		self.check_goal_compatible(start, goal)?;
+
+		let error = Cell::new(None);
+
+		// run A* / Dijkstra; once an erroneous condition is found, end the search at the next state
 		let result = astar(
 			start,
-			|state| self.successors(state),
+			|state| self.successors(state, goal, options, &error, trace),
 			|_| Cost(0),
-			|state| conditions_met(&goal.conditions, state),
+			|state| {
+				if error.get().is_some() {
+					return true;
+				}
+				let reached = self.conditions_hold(&goal.conditions, state, &error);
+				if reached && let Some(trace) = trace {
+					let mut trace = trace.borrow_mut();
+					let g_cost = trace.g_cost(state);
+					trace.tracer.reach_goal(state, g_cost);
+				}
+				reached
+			},
 		);
 
+		if let Some(condition) = error.get() {
+			return Err(PlannerError::ErroneousCondition(condition));
+		}
 		let (path, cost) = result.ok_or(PlannerError::NoPlanFound)?;
 
-		let mut action_indices = Vec::new();
+		let mut action_ids = Vec::new();
 
 		for window in path.windows(2) {
 			let from = &window[0];
 			let to = &window[1];
 
-			let action_index = self
-				.actions
-				.iter()
-				.enumerate()
-				.find_map(|(i, action)| {
-					if conditions_met(&action.preconditions, from) {
-						let next = from.with_effects(&action.effects);
-						if &next == to { Some(i) } else { None }
-					} else {
-						None
-					}
+			// the cheapest allowed action that leads from `from` to `to`, as the search would have used
+			let action_id = self
+				.actions()
+				.filter(|(id, action)| {
+					options.allows(*id)
+						&& goal.allows(action)
+						&& conditions_met(&action.preconditions, from)
+						&& &self.next_state(from, action) == to
 				})
+				.min_by_key(|(id, action)| options.cost(*id, action, from))
+				.map(|(id, _)| id)
 				.ok_or(PlannerError::UnreachableState)?;
 
-			action_indices.push(action_index);
+			action_ids.push(action_id);
 		}
 
 		Ok(Plan {
-			actions: action_indices,
+			actions: action_ids,
 			total_cost: cost,
 		})
 	}
 
+	/// Pass every `FactId` in every action through `remap`, e.g. after merging `FactMap`s.
+	pub fn remap_facts(&mut self, remap: &FactRemap) {
+		for action in self.actions.iter_mut().flatten() {
+			*action = action.remapped(remap);
+		}
+	}
+
+	/// The action of step `index` of `plan`, if both exist.
 	#[must_use]
 	pub fn get_plan_action(&self, plan: &Plan, index: usize) -> Option<&Action> {
-		if let Some(action_index) = plan.actions.get(index) {
-			self.actions.get(*action_index)
-		} else {
-			None
+		self.get_action(*plan.actions.get(index)?)
+	}
+
+	/// The state after each step of `plan`, starting from `start`, e.g. to `diff` consecutive steps.
+	///
+	/// # Errors
+	/// - `PlannerError::UnreachableState`: if a step's action no longer exists or its preconditions aren't met
+	/// - `PlannerError::IncompatibleState`: if an action references a fact `start` doesn't have
+	pub fn replay(&self, start: &WorldState, plan: &Plan) -> Result<Vec<WorldState>, PlannerError> {
+		self.check_compatible(start)?;
+		let mut states = Vec::with_capacity(plan.actions.len());
+		let mut state = start;
+		for id in &plan.actions {
+			let action = self
+				.get_action(*id)
+				.filter(|action| conditions_met(&action.preconditions, state))
+				.ok_or(PlannerError::UnreachableState)?;
+			states.push(self.next_state(state, action));
+			state = states.last().unwrap_or(start);
+		}
+		Ok(states)
+	}
+
+	/// The state after `action` is applied to `state`, with fact ranges enforced.
+	pub(crate) fn next_state(&self, state: &WorldState, action: &Action) -> WorldState {
+		let mut next = state.clone();
+		let _ = next.apply_effects_clamped(&action.effects, &self.ranges);
+		next
+	}
+
+	/// `conditions_met`, recording the first unknown condition in `error` under `ErrorPolicy::Fail`.
+	fn conditions_hold(
+		&self,
+		conditions: &[Condition],
+		state: &WorldState,
+		error: &Cell<Option<Condition>>,
+	) -> bool {
+		if let Some(met) = evaluate_conditions(conditions, state) {
+			return met;
 		}
+		if self.error_policy == ErrorPolicy::Fail && error.get().is_none() {
+			error.set(
+				conditions
+					.iter()
+					.find(|condition| condition.evaluate(state).is_none())
+					.copied(),
+			);
+		}
+		false
 	}
 
-	fn successors(&self, state: &WorldState) -> Vec<(WorldState, Cost)> {
-		let mut result = Vec::new();
+	fn successors(
+		&self,
+		state: &WorldState,
+		goal: &Goal,
+		options: &PlanOptions,
+		error: &Cell<Option<Condition>>,
+		trace: Option<&RefCell<Trace>>,
+	) -> Vec<(WorldState, Cost)> {
+		let mut result = Vec::with_capacity(self.actions.len());
+		let mut trace = trace.map(RefCell::borrow_mut);
+		let g_cost = trace.as_mut().map_or(Cost(0), |trace| {
+			let g_cost = trace.g_cost(state);
+			trace.tracer.expand(state, g_cost);
+			g_cost
+		});
 
-		for action in &self.actions {
-			if conditions_met(&action.preconditions, state) {
-				let next_state = state.with_effects(&action.effects);
-				result.push((next_state, action.cost));
+		let allowed =
+			|(id, action): &(ActionId, &Action)| options.allows(*id) && goal.allows(action);
+		for (id, action) in self.actions().filter(allowed) {
+			let cost = options.cost(id, action, state);
+			if !self.conditions_hold(&action.preconditions, state, error) {
+				if let Some(trace) = &mut trace {
+					let failed: Vec<Condition> = action
+						.preconditions
+						.iter()
+						.filter(|condition| !condition.is_satisfied(state))
+						.copied()
+						.collect();
+					trace.tracer.block(state, id, &failed);
+				}
+				continue;
+			}
+			// Actions that don't change anything only lead back to `state`; skip them before cloning.
+			if !state.is_changed_by(&action.effects, &self.ranges) {
+				if let Some(trace) = &mut trace {
+					trace.tracer.skip(state, id);
+				}
+				continue;
 			}
+			let next_state = self.next_state(state, action);
+			if let Some(trace) = &mut trace {
+				trace.record(&next_state, g_cost + cost);
+				trace.tracer.apply(state, id, &next_state, g_cost + cost);
+			}
+			result.push((next_state, cost));
 		}
 
 		result
@@ -97,3 +380,16 @@ impl Default for Planner {
 		Self::new()
 	}
 }
+
+fn check_facts(
+	mut facts: impl Iterator<Item = FactId>,
+	state: &WorldState,
+) -> Result<(), PlannerError> {
+	match facts.find(|fact| usize::from(fact.0) >= state.len()) {
+		Some(fact) => Err(PlannerError::IncompatibleState {
+			fact,
+			len: state.len(),
+		}),
+		None => Ok(()),
+	}
+}
@@ -0,0 +1,642 @@
+//! `FactMap`-aware formatting. The `format_*` methods write the same syntax `FactMap::parse_value`,
+//! `FactMap::parse_condition` and `FactMap::parse_effect` read, so parsed data can be written back
+//! out (see `Domain::to_schema` for whole data files). The `display_*` methods are for people: they
+//! also show things the parser can't read, such as `error` values and `#id` facts outside the map.
+
+use core::fmt::{Display, Formatter, Result, Write};
+
+use crate::simple::FactMap;
+use crate::{
+	Action, Condition, Effect, Explanation, FactChange, FactId, FormatError, Plan, Planner,
+	PlannerError, Value, WorldState,
+};
+
+/// Returned by `FactMap::display_fact`.
+#[derive(Clone, Copy, Debug)]
+pub struct DisplayFact<'a> {
+	facts: &'a FactMap,
+	fact: FactId,
+}
+
+/// Returned by `FactMap::display_value`.
+#[derive(Clone, Copy, Debug)]
+pub struct DisplayValue<'a> {
+	facts: &'a FactMap,
+	value: Value,
+}
+
+/// Returned by `FactMap::display_condition`.
+#[derive(Clone, Copy, Debug)]
+pub struct DisplayCondition<'a> {
+	facts: &'a FactMap,
+	condition: Condition,
+}
+
+/// Returned by `FactMap::display_effect`.
+#[derive(Clone, Copy, Debug)]
+pub struct DisplayEffect<'a> {
+	facts: &'a FactMap,
+	effect: Effect,
+}
+
+/// Returned by `FactMap::display_action`.
+#[derive(Clone, Copy, Debug)]
+pub struct DisplayAction<'a> {
+	facts: &'a FactMap,
+	action: &'a Action,
+}
+
+/// Returned by `FactMap::display_change`.
+#[derive(Clone, Copy, Debug)]
+pub struct DisplayChange<'a> {
+	facts: &'a FactMap,
+	change: FactChange,
+}
+
+/// Returned by `FactMap::display_changes`.
+#[derive(Clone, Copy, Debug)]
+pub struct DisplayChanges<'a> {
+	facts: &'a FactMap,
+	changes: &'a [FactChange],
+}
+
+/// Returned by `FactMap::display_explanation`.
+#[derive(Clone, Copy, Debug)]
+pub struct DisplayExplanation<'a> {
+	facts: &'a FactMap,
+	planner: &'a Planner,
+	explanation: &'a Explanation,
+}
+
+impl FactMap {
+	/// Formats as the fact's name, or `#id` if the fact is not in this map.
+	#[must_use]
+	pub fn display_fact(&self, fact: FactId) -> DisplayFact<'_> {
+		DisplayFact { facts: self, fact }
+	}
+
+	/// Formats as `3`, `my_fact`, or `error`.
+	#[must_use]
+	pub fn display_value(&self, value: Value) -> DisplayValue<'_> {
+		DisplayValue { facts: self, value }
+	}
+
+	/// Formats as `my_fact >= 3`.
+	#[must_use]
+	pub fn display_condition(&self, condition: &Condition) -> DisplayCondition<'_> {
+		DisplayCondition {
+			facts: self,
+			condition: *condition,
+		}
+	}
+
+	/// Formats as `my_fact += 3`.
+	#[must_use]
+	pub fn display_effect(&self, effect: &Effect) -> DisplayEffect<'_> {
+		DisplayEffect {
+			facts: self,
+			effect: *effect,
+		}
+	}
+
+	/// Formats on one line as `Chop Wood (cost 2): has_axe == 1 => has_wood = 1`, for reading only;
+	/// `Domain::to_schema` writes actions in a form that loads back.
+	#[must_use]
+	pub fn display_action<'a>(&'a self, action: &'a Action) -> DisplayAction<'a> {
+		DisplayAction {
+			facts: self,
+			action,
+		}
+	}
+
+	/// Formats as `hunger: 5 -> 3`.
+	#[must_use]
+	pub fn display_change(&self, change: &FactChange) -> DisplayChange<'_> {
+		DisplayChange {
+			facts: self,
+			change: *change,
+		}
+	}
+
+	/// Formats as `hunger: 5 -> 3, wood: 0 -> 1`, or `no change`.
+	#[must_use]
+	pub fn display_changes<'a>(&'a self, changes: &'a [FactChange]) -> DisplayChanges<'a> {
+		DisplayChanges {
+			facts: self,
+			changes,
+		}
+	}
+
+	/// Formats a `Planner::explain` report over several lines, e.g.
+	/// `never applicable: Chop Wood, blocked by has_axe == 1 in 4 states`.
+	#[must_use]
+	pub fn display_explanation<'a>(
+		&'a self,
+		planner: &'a Planner,
+		explanation: &'a Explanation,
+	) -> DisplayExplanation<'a> {
+		DisplayExplanation {
+			facts: self,
+			planner,
+			explanation,
+		}
+	}
+
+	/// `display_value`, for values that `parse_value` reads back as the same value.
+	///
+	/// # Errors
+	/// - `FormatError::ErrorValue`: for `Value::Error`, which has no syntax
+	/// - `FormatError::UnknownFact`: for a `Value::Ref` to a fact not in this map
+	pub fn format_value(&self, value: Value) -> core::result::Result<String, FormatError> {
+		self.check_value(value)?;
+		Ok(self.display_value(value).to_string())
+	}
+
+	/// `display_condition`, for conditions that `parse_condition` reads back as the same condition.
+	///
+	/// # Errors
+	/// See `format_value`; the condition's fact must be in this map too.
+	pub fn format_condition(
+		&self,
+		condition: &Condition,
+	) -> core::result::Result<String, FormatError> {
+		self.check_fact(condition.fact())?;
+		self.check_value(condition.value())?;
+		Ok(self.display_condition(condition).to_string())
+	}
+
+	/// `display_effect`, for effects that `parse_effect` reads back as the same effect.
+	///
+	/// # Errors
+	/// See `format_value`; the effect's fact must be in this map too.
+	pub fn format_effect(&self, effect: &Effect) -> core::result::Result<String, FormatError> {
+		self.check_fact(effect.fact())?;
+		self.check_value(effect.value())?;
+		Ok(self.display_effect(effect).to_string())
+	}
+
+	pub(crate) fn check_fact(&self, fact: FactId) -> core::result::Result<(), FormatError> {
+		if usize::from(fact.0) < self.len() {
+			Ok(())
+		} else {
+			Err(FormatError::UnknownFact(fact))
+		}
+	}
+
+	fn check_value(&self, value: Value) -> core::result::Result<(), FormatError> {
+		match value {
+			Value::Int(_) => Ok(()),
+			Value::Ref(fact) => self.check_fact(fact),
+			Value::Error => Err(FormatError::ErrorValue),
+		}
+	}
+
+	/// One line per step of `plan` naming the action and what it changed, e.g.
+	/// `1. Chop Wood: wood: 0 -> 1, energy: 5 -> 3`.
+	///
+	/// # Errors
+	/// See `Planner::replay`.
+	pub fn change_log(
+		&self,
+		planner: &Planner,
+		start: &WorldState,
+		plan: &Plan,
+	) -> core::result::Result<String, PlannerError> {
+		let states = planner.replay(start, plan)?;
+		let mut log = String::new();
+		let mut before = start;
+		for (step, after) in states.iter().enumerate() {
+			let name = planner
+				.get_plan_action(plan, step)
+				.map_or("?", |action| action.name.as_str());
+			let changes = before.diff(after);
+			let _ = writeln!(
+				log,
+				"{}. {name}: {}",
+				step + 1,
+				self.display_changes(&changes)
+			);
+			before = after;
+		}
+		Ok(log)
+	}
+}
+
+impl Display for DisplayFact<'_> {
+	fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+		match self.facts.get_fact_names().get(usize::from(self.fact.0)) {
+			Some(name) => write!(f, "{name}"),
+			None => write!(f, "#{}", self.fact.0),
+		}
+	}
+}
+
+impl Display for DisplayValue<'_> {
+	fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+		match self.value {
+			Value::Int(int) => write!(f, "{int}"),
+			Value::Ref(fact) => write!(f, "{}", self.facts.display_fact(fact)),
+			Value::Error => write!(f, "error"),
+		}
+	}
+}
+
+impl Display for DisplayCondition<'_> {
+	fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+		write!(
+			f,
+			"{} {} {}",
+			self.facts.display_fact(self.condition.fact()),
+			self.condition.operator(),
+			self.facts.display_value(self.condition.value())
+		)
+	}
+}
+
+impl Display for DisplayEffect<'_> {
+	fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+		write!(
+			f,
+			"{} {} {}",
+			self.facts.display_fact(self.effect.fact()),
+			self.effect.operator(),
+			self.facts.display_value(self.effect.value())
+		)
+	}
+}
+
+impl Display for DisplayChange<'_> {
+	fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+		write!(
+			f,
+			"{}: {} -> {}",
+			self.facts.display_fact(self.change.fact),
+			self.facts.display_value(self.change.old),
+			self.facts.display_value(self.change.new)
+		)
+	}
+}
+
+impl Display for DisplayChanges<'_> {
+	fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+		if self.changes.is_empty() {
+			return write!(f, "no change");
+		}
+		for (index, change) in self.changes.iter().enumerate() {
+			let separator = if index == 0 { "" } else { ", " };
+			write!(f, "{separator}{}", self.facts.display_change(change))?;
+		}
+		Ok(())
+	}
+}
+
+impl Display for DisplayExplanation<'_> {
+	fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+		let explanation = self.explanation;
+		let outcome = if explanation.goal_reached {
+			"goal reached"
+		} else {
+			"goal not reached"
+		};
+		let partial = if explanation.exhausted {
+			""
+		} else {
+			" (stopped early)"
+		};
+		writeln!(
+			f,
+			"{outcome} after exploring {} states{partial}",
+			explanation.states_explored
+		)?;
+		for condition in &explanation.unachievable {
+			writeln!(
+				f,
+				"unachievable: {}, no action changes {}",
+				self.facts.display_condition(condition),
+				self.facts.display_fact(condition.fact())
+			)?;
+		}
+		for condition in &explanation.never_satisfied {
+			writeln!(
+				f,
+				"never satisfied: {}",
+				self.facts.display_condition(condition)
+			)?;
+		}
+		for blocked in &explanation.never_applicable {
+			let name = self
+				.planner
+				.get_action(blocked.action)
+				.map_or("?", |action| action.name.as_str());
+			writeln!(
+				f,
+				"never applicable: {name}, blocked by {} in {} states",
+				self.facts.display_condition(&blocked.precondition),
+				blocked.blocked
+			)?;
+		}
+		Ok(())
+	}
+}
+
+impl Display for DisplayAction<'_> {
+	fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+		write!(f, "{} (cost {}):", self.action.name, self.action.cost.0)?;
+		for (index, condition) in self.action.preconditions.iter().enumerate() {
+			let separator = if index == 0 { " " } else { ", " };
+			write!(f, "{separator}{}", self.facts.display_condition(condition))?;
+		}
+		write!(f, " =>")?;
+		for (index, effect) in self.action.effects.iter().enumerate() {
+			let separator = if index == 0 { " " } else { ", " };
+			write!(f, "{separator}{}", self.facts.display_effect(effect))?;
+		}
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{ActionId, Cost, Goal};
+
+	/// A tiny xorshift generator so the round-trip tests are repeatable without extra dependencies.
+	struct Rng(u64);
+
+	impl Rng {
+		fn next(&mut self, bound: usize) -> usize {
+			self.0 ^= self.0 << 13;
+			self.0 ^= self.0 >> 7;
+			self.0 ^= self.0 << 17;
+			usize::try_from(self.0 % bound as u64).unwrap()
+		}
+
+		fn value(&mut self, facts: &[FactId]) -> Value {
+			match self.next(6) {
+				0 => Value::Ref(facts[self.next(facts.len())]),
+				1 => Value::Int(i64::MIN + i64::try_from(self.next(3)).unwrap()),
+				2 => Value::Error,
+				3 => Value::Ref(FactId(99)),
+				_ => Value::Int(i64::try_from(self.next(2001)).unwrap() - 1000),
+			}
+		}
+
+		/// A value `format_value` accepts.
+		#[cfg(feature = "serde")]
+		fn known_value(&mut self, facts: &[FactId]) -> Value {
+			loop {
+				let value = self.value(facts);
+				if unformattable(value).is_none() {
+					return value;
+				}
+			}
+		}
+	}
+
+	/// The error `format_value` should give for `value` with the map from `facts()`.
+	fn unformattable(value: Value) -> Option<FormatError> {
+		match value {
+			Value::Error => Some(FormatError::ErrorValue),
+			Value::Ref(fact) if fact.0 >= 5 => Some(FormatError::UnknownFact(fact)),
+			_ => None,
+		}
+	}
+
+	fn facts() -> (FactMap, Vec<FactId>) {
+		let mut facts = FactMap::new();
+		let ids = ["hp", "gold", "has_axe", "_hidden", "wood2"]
+			.iter()
+			.map(|name| facts.new_fact(*name).unwrap())
+			.collect();
+		(facts, ids)
+	}
+
+	#[test]
+	fn values_round_trip() {
+		let (facts, ids) = facts();
+		let mut rng = Rng(0xfa1);
+		for _ in 0..1000 {
+			let value = rng.value(&ids);
+			let formatted = facts.format_value(value);
+			assert_eq!(formatted.as_ref().err().copied(), unformattable(value));
+			if let Ok(text) = formatted {
+				let parsed = facts.parse_value(&text);
+				assert_eq!(format!("{parsed:?}"), format!("{value:?}"), "{text}");
+			}
+		}
+		assert_eq!(
+			facts.format_effect(&Effect::Set(FactId(7), Value::Int(1))),
+			Err(FormatError::UnknownFact(FactId(7)))
+		);
+	}
+
+	#[test]
+	fn conditions_round_trip() {
+		let (facts, ids) = facts();
+		let mut rng = Rng(0x5eed);
+		for _ in 0..1000 {
+			let fact = ids[rng.next(ids.len())];
+			let value = rng.value(&ids);
+			let condition = match rng.next(6) {
+				0 => Condition::Eq(fact, value),
+				1 => Condition::Ne(fact, value),
+				2 => Condition::Gt(fact, value),
+				3 => Condition::Lt(fact, value),
+				4 => Condition::Ge(fact, value),
+				_ => Condition::Le(fact, value),
+			};
+			let formatted = facts.format_condition(&condition);
+			assert_eq!(formatted.as_ref().err().copied(), unformattable(value));
+			if let Ok(text) = formatted {
+				let parsed = facts.parse_condition(&text).expect(&text);
+				assert_eq!(format!("{parsed:?}"), format!("{condition:?}"), "{text}");
+			}
+		}
+	}
+
+	#[test]
+	fn effects_round_trip() {
+		let (facts, ids) = facts();
+		let mut rng = Rng(0xd06);
+		for _ in 0..1000 {
+			let fact = ids[rng.next(ids.len())];
+			let value = rng.value(&ids);
+			let effect = match rng.next(3) {
+				0 => Effect::Set(fact, value),
+				1 => Effect::Add(fact, value),
+				_ => Effect::Sub(fact, value),
+			};
+			let formatted = facts.format_effect(&effect);
+			assert_eq!(formatted.as_ref().err().copied(), unformattable(value));
+			if let Ok(text) = formatted {
+				let parsed = facts.parse_effect(&text).expect(&text);
+				assert_eq!(format!("{parsed:?}"), format!("{effect:?}"), "{text}");
+			}
+		}
+	}
+
+	#[cfg(feature = "serde")]
+	#[test]
+	fn actions_round_trip() {
+		use crate::simple::Domain;
+		use crate::{Executor, ExecutorArg, ExportError};
+
+		let (facts, ids) = facts();
+		let mut rng = Rng(0xac7);
+		let mut planner = Planner::new();
+		for index in 0..200 {
+			let cost = Cost(i32::try_from(rng.next(10)).unwrap());
+			let mut action = Action::new(format!("Action {index}"), cost);
+			for _ in 0..rng.next(3) {
+				let fact = ids[rng.next(ids.len())];
+				let value = rng.known_value(&ids);
+				action.push_precondition(match rng.next(3) {
+					0 => Condition::Eq(fact, value),
+					1 => Condition::Ge(fact, value),
+					_ => Condition::Ne(fact, value),
+				});
+			}
+			for _ in 0..rng.next(3) {
+				let fact = ids[rng.next(ids.len())];
+				let value = rng.known_value(&ids);
+				action.push_effect(match rng.next(3) {
+					0 => Effect::Set(fact, value),
+					1 => Effect::Add(fact, value),
+					_ => Effect::Sub(fact, value),
+				});
+			}
+			if rng.next(2) == 0 {
+				action.push_executorv(
+					Executor::new("use")
+						.with_arg(ExecutorArg::Fact(ids[rng.next(ids.len())]))
+						.with_arg(ExecutorArg::Text(" a:b\\ ".into()))
+						.with_arg(ExecutorArg::Text("7".into()))
+						.with_arg(ExecutorArg::Int(-7))
+						.with_arg(ExecutorArg::Param("who".into())),
+				);
+				action.push_tag("tool");
+			}
+			planner.push_action(action);
+		}
+		let mut domain = Domain {
+			initial_state: facts.new_world_state().with_fact(ids[0], Value::Int(5)),
+			facts,
+			planner,
+			goals: vec![Goal::new("Idle")],
+		};
+
+		let reloaded = Domain::from_schema(&domain.to_schema().unwrap()).unwrap();
+		let actions = |domain: &Domain| {
+			domain
+				.planner
+				.actions()
+				.map(|(_, action)| format!("{action:?}"))
+				.collect::<Vec<_>>()
+		};
+		assert_eq!(actions(&reloaded), actions(&domain));
+		assert_eq!(reloaded.initial_state, domain.initial_state);
+		assert_eq!(
+			format!("{:?}", reloaded.goals),
+			format!("{:?}", domain.goals)
+		);
+
+		let mut broken = Action::new("Broken", Cost(1));
+		broken.push_effect(Effect::Set(ids[0], Value::Error));
+		domain.planner.push_action(broken);
+		assert!(matches!(
+			domain.to_schema(),
+			Err(ExportError::Effect { ref action, index: 0, source: FormatError::ErrorValue })
+				if action == "Broken"
+		));
+		domain.initial_state.set(ids[1], Value::Ref(ids[0]));
+		assert_eq!(
+			domain.to_schema().unwrap_err(),
+			ExportError::InitialNotALiteral("gold".into())
+		);
+	}
+
+	#[test]
+	fn action_is_readable() {
+		let (facts, ids) = facts();
+		let mut action = Action::new("Chop Wood", Cost(2));
+		action
+			.push_precondition(Condition::Eq(ids[2], Value::TRUE))
+			.push_effect(Effect::Add(ids[4], Value::Int(1)))
+			.push_effect(Effect::Sub(ids[0], Value::Ref(ids[1])));
+		assert_eq!(
+			facts.display_action(&action).to_string(),
+			"Chop Wood (cost 2): has_axe == 1 => wood2 += 1, hp -= gold"
+		);
+	}
+
+	#[test]
+	fn change_log_is_readable() {
+		let (facts, ids) = facts();
+		let mut chop = Action::new("Chop Wood", Cost(2));
+		chop.push_precondition(Condition::Eq(ids[2], Value::TRUE))
+			.push_effect(Effect::Add(ids[4], Value::Int(1)))
+			.push_effect(Effect::Sub(ids[0], Value::Int(3)));
+		let mut drop = Action::new("Drop Axe", Cost(1));
+		drop.push_effect(Effect::Set(ids[2], Value::FALSE));
+		let mut planner = Planner::new();
+		planner.push_action(chop);
+		planner.push_action(drop);
+
+		let start = facts
+			.new_world_state()
+			.with_fact(ids[0], Value::Int(10))
+			.with_fact(ids[2], Value::TRUE);
+		let plan = Plan {
+			actions: vec![ActionId(0), ActionId(1)],
+			total_cost: Cost(3),
+		};
+		assert_eq!(
+			facts.change_log(&planner, &start, &plan).unwrap(),
+			"1. Chop Wood: hp: 10 -> 7, wood2: 0 -> 1\n2. Drop Axe: has_axe: 1 -> 0\n"
+		);
+
+		let swapped = Plan {
+			actions: vec![ActionId(1), ActionId(0)],
+			total_cost: Cost(3),
+		};
+		assert!(matches!(
+			facts.change_log(&planner, &start, &swapped),
+			Err(PlannerError::UnreachableState)
+		));
+	}
+
+	#[test]
+	fn explanation_is_readable() {
+		let (facts, ids) = facts();
+		let (hp, gold, has_axe, wood) = (ids[0], ids[1], ids[2], ids[4]);
+		let mut chop = Action::new("Chop Wood", Cost(2));
+		chop.push_precondition(Condition::Eq(has_axe, Value::TRUE))
+			.push_precondition(Condition::Ge(hp, Value::Int(1)))
+			.push_effect(Effect::Set(wood, Value::TRUE));
+		let mut mine = Action::new("Mine", Cost(1));
+		mine.push_precondition(Condition::Lt(gold, Value::Int(3)))
+			.push_effect(Effect::Add(gold, Value::Int(1)));
+		let mut planner = Planner::new();
+		planner.push_action(chop);
+		planner.push_action(mine);
+
+		let mut goal = Goal::new("Rich and rested");
+		goal.push_condition(Condition::Eq(wood, Value::TRUE))
+			.push_condition(Condition::Ge(gold, Value::Int(2)))
+			.push_condition(Condition::Ge(hp, Value::Int(5)));
+		let start = facts.new_world_state();
+		let explanation = planner.explain(&start, &goal).unwrap();
+		assert!(!explanation.goal_reached && explanation.exhausted);
+		assert_eq!(explanation.states_explored, 4);
+		assert_eq!(
+			facts
+				.display_explanation(&planner, &explanation)
+				.to_string(),
+			"goal not reached after exploring 4 states\n\
+			 unachievable: hp >= 5, no action changes hp\n\
+			 never satisfied: wood2 == 1\n\
+			 never satisfied: hp >= 5\n\
+			 never applicable: Chop Wood, blocked by has_axe == 1 in 4 states\n"
+		);
+	}
+}
@@ -0,0 +1,640 @@
+use serde::{Deserialize, Serialize};
+
+use crate::errors::{DomainError, ExportError};
+use crate::simple::{FactInfo, FactKind, FactMap};
+use crate::{Action, Cost, ExecutorArg, FactId, Goal, Planner, Value, WorldState};
+
+/// The on-disk shape of a [`Domain`], as read from JSON or RON.
+/// Unknown fields are ignored so data files can carry extra game data.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct DomainSchema {
+	#[serde(default)]
+	pub facts: Vec<FactSchema>,
+	/// Overrides fact defaults; anything not listed keeps its default. Read and written as a map, in
+	/// order, so `Domain::to_schema` can list facts in `FactId` order.
+	#[serde(default, with = "entries")]
+	pub initial_state: Vec<(String, LiteralSchema)>,
+	#[serde(default)]
+	pub actions: Vec<ActionSchema>,
+	#[serde(default)]
+	pub goals: Vec<GoalSchema>,
+}
+
+/// (De)serializes `DomainSchema::initial_state` as a map, keeping its order.
+mod entries {
+	use core::fmt;
+
+	use serde::de::{MapAccess, Visitor};
+	use serde::{Deserializer, Serializer};
+
+	use super::LiteralSchema;
+
+	pub(super) fn serialize<S: Serializer>(
+		entries: &[(String, LiteralSchema)],
+		serializer: S,
+	) -> Result<S::Ok, S::Error> {
+		serializer.collect_map(entries.iter().map(|(name, value)| (name, value)))
+	}
+
+	pub(super) fn deserialize<'de, D: Deserializer<'de>>(
+		deserializer: D,
+	) -> Result<Vec<(String, LiteralSchema)>, D::Error> {
+		deserializer.deserialize_map(EntriesVisitor)
+	}
+
+	struct EntriesVisitor;
+
+	impl<'de> Visitor<'de> for EntriesVisitor {
+		type Value = Vec<(String, LiteralSchema)>;
+
+		fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+			formatter.write_str("a map of fact names to values")
+		}
+
+		fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+			let mut entries = Vec::with_capacity(map.size_hint().unwrap_or(0));
+			while let Some(entry) = map.next_entry()? {
+				entries.push(entry);
+			}
+			Ok(entries)
+		}
+	}
+}
+
+/// Either `"name"` or
+/// `{ "name": "hp", "type": "int", "default": 10, "min": 0, "max": 10, "description": "Hit points" }`
+/// where every field but `name` is optional.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum FactSchema {
+	Name(String),
+	Full {
+		name: String,
+		#[serde(default, rename = "type")]
+		kind: FactKind,
+		#[serde(default)]
+		default: LiteralSchema,
+		#[serde(default)]
+		min: Option<i64>,
+		#[serde(default)]
+		max: Option<i64>,
+		#[serde(default)]
+		description: Option<String>,
+	},
+}
+
+impl FactSchema {
+	#[must_use]
+	pub fn name(&self) -> &str {
+		match self {
+			FactSchema::Name(name) | FactSchema::Full { name, .. } => name,
+		}
+	}
+
+	#[must_use]
+	pub fn info(&self) -> FactInfo {
+		match self {
+			FactSchema::Name(_) => FactInfo::default(),
+			FactSchema::Full {
+				kind,
+				default,
+				min,
+				max,
+				description,
+				..
+			} => FactInfo {
+				kind: *kind,
+				default: default.to_int(),
+				min: *min,
+				max: *max,
+				description: description.clone(),
+			},
+		}
+	}
+}
+
+/// Either `3` or `true`/`false`.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum LiteralSchema {
+	Int(i64),
+	Bool(bool),
+}
+
+impl LiteralSchema {
+	#[must_use]
+	pub fn to_int(self) -> i64 {
+		match self {
+			LiteralSchema::Int(int) => int,
+			LiteralSchema::Bool(bool) => i64::from(bool),
+		}
+	}
+}
+
+impl Default for LiteralSchema {
+	fn default() -> Self {
+		LiteralSchema::Int(0)
+	}
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ActionSchema {
+	pub name: String,
+	pub cost: CostSchema,
+	#[serde(default)]
+	pub preconditions: Vec<String>,
+	#[serde(default)]
+	pub effects: Vec<String>,
+	#[serde(default)]
+	pub executors: Vec<ExecutorSchema>,
+	#[serde(default)]
+	pub tags: Vec<String>,
+}
+
+/// Either `3` or `"3"`.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum CostSchema {
+	Int(i32),
+	Text(String),
+}
+
+/// Either `["kind", "args"]` or `{ "kind": "kind", "args": "args" }`, see `FactMap::parse_executor`.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum ExecutorSchema {
+	Pair(String, String),
+	Full {
+		kind: String,
+		#[serde(default)]
+		args: String,
+	},
+}
+
+/// Either `["name", ["condition", ...]]` or
+/// `{ "name": "name", "conditions": ["condition", ...], "allowed_tags": ["tag", ...] }`.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum GoalSchema {
+	Pair(String, Vec<String>),
+	Full {
+		name: String,
+		#[serde(default)]
+		conditions: Vec<String>,
+		#[serde(default)]
+		allowed_tags: Vec<String>,
+	},
+}
+
+/// Everything needed to run an agent, loaded from a single data file.
+#[derive(Clone, Debug)]
+pub struct Domain {
+	pub facts: FactMap,
+	pub planner: Planner,
+	pub initial_state: WorldState,
+	pub goals: Vec<Goal>,
+}
+
+impl Domain {
+	/// Requires the `json` feature.
+	///
+	/// # Errors
+	/// - `DomainError::Json` if the string is not valid JSON for a `DomainSchema`
+	/// - Any error from `Domain::from_schema`
+	#[cfg(feature = "json")]
+	pub fn from_json(json: &str) -> Result<Self, DomainError> {
+		let schema: DomainSchema = serde_json::from_str(json)?;
+		Self::from_schema(&schema)
+	}
+
+	/// Requires the `ron` feature.
+	///
+	/// # Errors
+	/// - `DomainError::Ron` if the string is not valid RON for a `DomainSchema`
+	/// - Any error from `Domain::from_schema`
+	#[cfg(feature = "ron")]
+	pub fn from_ron(ron: &str) -> Result<Self, DomainError> {
+		let schema: DomainSchema = ron::from_str(ron)?;
+		Self::from_schema(&schema)
+	}
+
+	/// # Errors
+	/// - `DomainError::Fact` if a fact name is rejected by `FactMap::new_fact`
+	/// - `DomainError::UnknownInitialFact` if `initial_state` names an undeclared fact
+	/// - `DomainError::InitialOutOfRange` if an `initial_state` value is outside of its fact's range
+	/// - `DomainError::Cost` if an action's cost is not an `i32`
+	/// - `DomainError::Precondition`, `DomainError::Effect`, `DomainError::Executor`, or
+	///   `DomainError::GoalCondition` if the string at that position fails to parse
+	/// - `DomainError::PreconditionRange` or `DomainError::GoalConditionRange` if a condition fails `FactMap::check_condition`
+	pub fn from_schema(schema: &DomainSchema) -> Result<Self, DomainError> {
+		let mut facts = FactMap::new();
+		for fact in &schema.facts {
+			let _ = facts
+				.new_fact_with(fact.name(), fact.info())
+				.map_err(|source| DomainError::Fact {
+					name: fact.name().into(),
+					source,
+				})?;
+		}
+
+		let mut initial_state = facts.new_world_state();
+		for (name, value) in &schema.initial_state {
+			let Some(fact_id) = facts.get_fact_id(name) else {
+				return Err(DomainError::UnknownInitialFact(name.clone()));
+			};
+			let value = value.to_int();
+			if let Some(info) = facts.get_fact_info(fact_id)
+				&& !info.range().contains(value)
+			{
+				return Err(DomainError::InitialOutOfRange {
+					name: name.clone(),
+					value,
+				});
+			}
+			initial_state.set(fact_id, Value::Int(value));
+		}
+
+		let mut planner = Planner::new();
+		planner.set_fact_ranges(facts.get_fact_ranges());
+		for a in &schema.actions {
+			planner.push_action(Self::load_action(&facts, a)?);
+		}
+
+		let mut goals = Vec::new();
+		for g in &schema.goals {
+			let (name, conditions, allowed_tags) = match g {
+				GoalSchema::Pair(name, conditions) => (name, conditions, &[][..]),
+				GoalSchema::Full {
+					name,
+					conditions,
+					allowed_tags,
+				} => (name, conditions, &allowed_tags[..]),
+			};
+			let mut goal = Goal::new(name);
+			goal.allowed_tags = allowed_tags.to_vec();
+			for (index, condition) in conditions.iter().enumerate() {
+				let condition = facts.parse_condition(condition).map_err(|source| {
+					DomainError::GoalCondition {
+						goal: name.clone(),
+						index,
+						source: Box::new(source),
+					}
+				})?;
+				facts.check_condition(&condition).map_err(|source| {
+					DomainError::GoalConditionRange {
+						goal: name.clone(),
+						index,
+						source,
+					}
+				})?;
+				goal.push_condition(condition);
+			}
+			goals.push(goal);
+		}
+
+		Ok(Self {
+			facts,
+			planner,
+			initial_state,
+			goals,
+		})
+	}
+
+	fn load_action(facts: &FactMap, a: &ActionSchema) -> Result<Action, DomainError> {
+		let cost = match &a.cost {
+			CostSchema::Int(cost) => *cost,
+			CostSchema::Text(text) => text.trim().parse().map_err(|_| DomainError::Cost {
+				action: a.name.clone(),
+				cost: text.clone(),
+			})?,
+		};
+		let mut action = Action::new(&a.name, Cost(cost));
+		for (index, precondition) in a.preconditions.iter().enumerate() {
+			let condition = facts.parse_condition(precondition).map_err(|source| {
+				DomainError::Precondition {
+					action: a.name.clone(),
+					index,
+					source: Box::new(source),
+				}
+			})?;
+			facts
+				.check_condition(&condition)
+				.map_err(|source| DomainError::PreconditionRange {
+					action: a.name.clone(),
+					index,
+					source,
+				})?;
+			action.push_precondition(condition);
+		}
+		for (index, effect) in a.effects.iter().enumerate() {
+			let effect = facts
+				.parse_effect(effect)
+				.map_err(|source| DomainError::Effect {
+					action: a.name.clone(),
+					index,
+					source: Box::new(source),
+				})?;
+			action.push_effect(effect);
+		}
+		for (index, executor) in a.executors.iter().enumerate() {
+			let (kind, args) = match executor {
+				ExecutorSchema::Pair(kind, args) | ExecutorSchema::Full { kind, args } => {
+					(kind, args)
+				}
+			};
+			let executor =
+				facts
+					.parse_executor(kind, args)
+					.map_err(|source| DomainError::Executor {
+						action: a.name.clone(),
+						index,
+						source,
+					})?;
+			action.push_executorv(executor);
+		}
+		action.tags.clone_from(&a.tags);
+		Ok(action)
+	}
+
+	/// The schema `from_schema` turns back into this domain, e.g. to save it as JSON or RON after
+	/// editing it in a tool. Actions keep their order, so removed actions leave no gaps in the
+	/// reloaded `ActionId`s. Only the facts, initial state, actions and goals are written.
+	///
+	/// # Errors
+	/// - `ExportError::InitialNotALiteral` if the initial state holds a `Value::Ref` or `Value::Error`
+	/// - `ExportError::Precondition`, `ExportError::Effect`, `ExportError::Executor`, or
+	///   `ExportError::GoalCondition` if something there refers to `Value::Error` or a fact
+	///   outside of `facts`, which the loader can't read back (see `FactMap::format_value`)
+	pub fn to_schema(&self) -> Result<DomainSchema, ExportError> {
+		let mut schema = DomainSchema::default();
+		for (index, name) in self.facts.get_fact_names().iter().enumerate() {
+			let fact = FactId(u16::try_from(index).unwrap_or(u16::MAX));
+			let info = self.facts.get_fact_info(fact).cloned().unwrap_or_default();
+			match self.initial_state.try_get(fact) {
+				Ok(Value::Int(int)) if int == info.default => {}
+				Ok(Value::Int(int)) => {
+					schema
+						.initial_state
+						.push((name.clone(), LiteralSchema::Int(int)));
+				}
+				_ => return Err(ExportError::InitialNotALiteral(name.clone())),
+			}
+			schema.facts.push(if info == FactInfo::default() {
+				FactSchema::Name(name.clone())
+			} else {
+				FactSchema::Full {
+					name: name.clone(),
+					kind: info.kind,
+					default: LiteralSchema::Int(info.default),
+					min: info.min,
+					max: info.max,
+					description: info.description,
+				}
+			});
+		}
+
+		for (_, action) in self.planner.actions() {
+			schema.actions.push(self.save_action(action)?);
+		}
+
+		for goal in &self.goals {
+			let conditions = goal
+				.conditions
+				.iter()
+				.enumerate()
+				.map(|(index, condition)| {
+					self.facts.format_condition(condition).map_err(|source| {
+						ExportError::GoalCondition {
+							goal: goal.name.clone(),
+							index,
+							source,
+						}
+					})
+				})
+				.collect::<Result<_, _>>()?;
+			schema.goals.push(GoalSchema::Full {
+				name: goal.name.clone(),
+				conditions,
+				allowed_tags: goal.allowed_tags.clone(),
+			});
+		}
+		Ok(schema)
+	}
+
+	fn save_action(&self, action: &Action) -> Result<ActionSchema, ExportError> {
+		let facts = &self.facts;
+		let mut a = ActionSchema {
+			name: action.name.clone(),
+			cost: CostSchema::Int(action.cost.0),
+			preconditions: Vec::new(),
+			effects: Vec::new(),
+			executors: Vec::new(),
+			tags: action.tags.clone(),
+		};
+		for (index, condition) in action.preconditions.iter().enumerate() {
+			let condition =
+				facts
+					.format_condition(condition)
+					.map_err(|source| ExportError::Precondition {
+						action: action.name.clone(),
+						index,
+						source,
+					})?;
+			a.preconditions.push(condition);
+		}
+		for (index, effect) in action.effects.iter().enumerate() {
+			let effect = facts
+				.format_effect(effect)
+				.map_err(|source| ExportError::Effect {
+					action: action.name.clone(),
+					index,
+					source,
+				})?;
+			a.effects.push(effect);
+		}
+		for (index, executor) in action.executors.iter().enumerate() {
+			let mut args = String::new();
+			for (arg_index, arg) in executor.args.iter().enumerate() {
+				if arg_index > 0 {
+					args.push(':');
+				}
+				match arg {
+					// by name, as a data file would
+					ExecutorArg::Fact(fact) => {
+						facts
+							.check_fact(*fact)
+							.map_err(|source| ExportError::Executor {
+								action: action.name.clone(),
+								index,
+								source,
+							})?;
+						args.push('@');
+						args.push_str(&facts.display_fact(*fact).to_string());
+					}
+					arg => args.push_str(&arg.to_string()),
+				}
+			}
+			a.executors.push(ExecutorSchema::Full {
+				kind: executor.kind.clone(),
+				args,
+			});
+		}
+		Ok(a)
+	}
+
+	/// Find a goal by name.
+	#[must_use]
+	pub fn get_goal(&self, name: &str) -> Option<&Goal> {
+		self.goals.iter().find(|goal| goal.name == name)
+	}
+}
+
+#[cfg(all(test, any(feature = "json", feature = "ron")))]
+mod tests {
+	use super::*;
+
+	#[cfg(feature = "json")]
+	const DEER_JSON: &str = r#"
+	{
+		"name_en": "Deer",
+		"facts": [
+			"satiety",
+			{"name": "is_threatened", "type": "bool", "default": false},
+			{"name": "energy_level", "default": 25, "min": 0, "max": 26}
+		],
+		"initial_state": { "satiety": 50 },
+		"goals": [
+			["Eat", ["satiety >= 53"]],
+			{"name": "Calm", "conditions": ["is_threatened == 0"], "allowed_tags": ["stealth"]}
+		],
+		"actions": [
+			{
+				"name": "Graze",
+				"cost": 3,
+				"preconditions": ["is_threatened == 0"],
+				"effects": ["energy_level += 1", "satiety += 3"],
+				"executors": [["locate", "tall_grass"], {"kind": "wait", "args": "3"}],
+				"tags": ["feeding"]
+			}
+		]
+	}
+	"#;
+
+	#[cfg(feature = "ron")]
+	const DEER_RON: &str = r#"
+	(
+		facts: ["satiety", "is_threatened", (name: "energy_level", default: 25)],
+		initial_state: { "satiety": 50 },
+		goals: [("Eat", ["satiety >= 53"])],
+		actions: [
+			(
+				name: "Graze",
+				cost: "3",
+				preconditions: ["is_threatened == 0"],
+				effects: ["energy_level += 1", "satiety += 3"],
+				executors: [("locate", "tall_grass")],
+			),
+		],
+	)
+	"#;
+
+	fn check_deer(domain: &Domain) {
+		let energy_level = domain.facts.get_fact_id("energy_level").unwrap();
+		assert!(
+			domain
+				.initial_state
+				.get(energy_level)
+				.eq(&Value::Int(25), &domain.initial_state)
+		);
+		let goal = domain.get_goal("Eat").expect("missing goal");
+		let plan = domain
+			.planner
+			.plan(&domain.initial_state, goal)
+			.expect("no plan found");
+		assert_eq!(plan.actions.len(), 1);
+		assert_eq!(plan.total_cost, Cost(3));
+	}
+
+	#[cfg(feature = "json")]
+	#[test]
+	fn loads_json() {
+		let domain = Domain::from_json(DEER_JSON).expect("failed to load");
+		assert_eq!(domain.goals.len(), 2);
+		check_deer(&domain);
+		let graze = domain.planner.get_action_by_name("Graze").unwrap();
+		assert!(graze.has_tag("feeding"));
+		let calm = domain.get_goal("Calm").unwrap();
+		assert_eq!(calm.allowed_tags, vec!["stealth".to_string()]);
+		assert!(!calm.allows(graze));
+
+		// saved and loaded again unchanged
+		let json = serde_json::to_string(&domain.to_schema().unwrap()).unwrap();
+		let reloaded = Domain::from_json(&json).expect("failed to reload");
+		check_deer(&reloaded);
+		assert_eq!(
+			reloaded.facts.get_fact_names(),
+			domain.facts.get_fact_names()
+		);
+		assert_eq!(
+			reloaded.facts.get_fact_ranges(),
+			domain.facts.get_fact_ranges()
+		);
+		assert_eq!(reloaded.initial_state, domain.initial_state);
+		assert_eq!(
+			format!("{:?}", reloaded.goals),
+			format!("{:?}", domain.goals)
+		);
+		assert_eq!(
+			format!(
+				"{:?}",
+				reloaded.planner.get_action_by_name("Graze").unwrap()
+			),
+			format!("{graze:?}")
+		);
+
+		// facts are exported in `FactId` order, so saving again doesn't reorder a data file
+		let mut domain = domain;
+		for (name, value) in [("energy_level", 20), ("is_threatened", 1)] {
+			let fact = domain.facts.get_fact_id(name).unwrap();
+			domain.initial_state.set(fact, Value::Int(value));
+		}
+		let schema = domain.to_schema().unwrap();
+		let names: Vec<&str> = schema
+			.initial_state
+			.iter()
+			.map(|(name, _)| name.as_str())
+			.collect();
+		assert_eq!(names, ["satiety", "is_threatened", "energy_level"]);
+		let json = serde_json::to_string(&schema).unwrap();
+		assert!(
+			json.contains(r#""initial_state":{"satiety":50,"is_threatened":1,"energy_level":20}"#)
+		);
+		let reloaded = Domain::from_json(&json).expect("failed to reload");
+		assert_eq!(reloaded.initial_state, domain.initial_state);
+	}
+
+	#[cfg(feature = "ron")]
+	#[test]
+	fn loads_ron() {
+		let domain = Domain::from_ron(DEER_RON).expect("failed to load");
+		check_deer(&domain);
+	}
+
+	#[cfg(feature = "json")]
+	#[test]
+	fn errors_point_at_action_and_field() {
+		let json = DEER_JSON.replace("satiety += 3", "satiety += hunger");
+		let err = Domain::from_json(&json).expect_err("should fail");
+		assert!(matches!(
+			err,
+			DomainError::Effect { ref action, index: 1, .. } if action == "Graze"
+		));
+
+		let json = DEER_JSON.replace("is_threatened == 0", "is_threatened == 2");
+		let err = Domain::from_json(&json).expect_err("should fail");
+		assert!(matches!(
+			err,
+			DomainError::PreconditionRange { ref action, index: 0, .. } if action == "Graze"
+		));
+	}
+}
@@ -0,0 +1,177 @@
+//! Graphviz DOT export of action libraries and search trees, as plain text; render with e.g.
+//! `dot -Tsvg`.
+
+use core::fmt::Write;
+
+use crate::simple::FactMap;
+use crate::{Effect, Planner, SearchTree};
+
+impl FactMap {
+	/// One node per action, with an edge from `a` to `b` (labelled with the facts involved) when an
+	/// effect of `a` writes a fact that a precondition of `b` reads.
+	#[must_use]
+	pub fn action_graph_dot(&self, planner: &Planner) -> String {
+		let mut dot = String::from("digraph actions {\n\tnode [shape=box];\n");
+		for (id, action) in planner.actions() {
+			let _ = writeln!(dot, "\ta{} [label=\"{}\"];", id.0, escape(&action.name));
+		}
+		for (from, action) in planner.actions() {
+			for (to, other) in planner.actions() {
+				let mut facts: Vec<_> = action
+					.effects
+					.iter()
+					.map(Effect::fact)
+					.filter(|fact| {
+						other
+							.preconditions
+							.iter()
+							.any(|condition| condition.referenced_facts().any(|read| read == *fact))
+					})
+					.collect();
+				facts.sort_unstable();
+				facts.dedup();
+				if facts.is_empty() {
+					continue;
+				}
+				let label = facts
+					.iter()
+					.map(|fact| self.display_fact(*fact).to_string())
+					.collect::<Vec<_>>()
+					.join(", ");
+				let _ = writeln!(
+					dot,
+					"\ta{} -> a{} [label=\"{}\"];",
+					from.0,
+					to.0,
+					escape(&label)
+				);
+			}
+		}
+		dot.push_str("}\n");
+		dot
+	}
+
+	/// The states and transitions recorded by a `SearchTree` tracer. The start node lists every fact;
+	/// other nodes list the facts that differ from the start, and their cost. The goal node has a
+	/// double border, and states that were never expanded are dashed.
+	#[must_use]
+	pub fn search_tree_dot(&self, planner: &Planner, tree: &SearchTree) -> String {
+		let mut dot = String::from("digraph search {\n\tnode [shape=box];\n");
+		let start = tree.nodes.first().map(|node| &node.state);
+		for (index, node) in tree.nodes.iter().enumerate() {
+			let lines: Vec<String> = if index == 0 {
+				self.view(&node.state)
+					.iter()
+					.map(|(name, value)| format!("{name} = {}", self.display_value(value)))
+					.collect()
+			} else {
+				let changes = start
+					.map(|start| start.diff(&node.state))
+					.unwrap_or_default();
+				core::iter::once(format!("g = {}", node.g_cost.0))
+					.chain(changes.iter().map(|change| {
+						format!(
+							"{} = {}",
+							self.display_fact(change.fact),
+							self.display_value(change.new)
+						)
+					}))
+					.collect()
+			};
+			let mut attributes = format!("label=\"{}\"", label(&lines));
+			if tree.goal == Some(index) {
+				attributes.push_str(", peripheries=2");
+			}
+			if !node.expanded {
+				attributes.push_str(", style=dashed");
+			}
+			let _ = writeln!(dot, "\tn{index} [{attributes}];");
+		}
+		for edge in &tree.edges {
+			let name = planner
+				.get_action(edge.action)
+				.map_or("?", |action| action.name.as_str());
+			let _ = writeln!(
+				dot,
+				"\tn{} -> n{} [label=\"{}\"];",
+				edge.from,
+				edge.to,
+				escape(name)
+			);
+		}
+		dot.push_str("}\n");
+		dot
+	}
+}
+
+/// Escape `"` and `\` for a DOT string.
+fn escape(text: &str) -> String {
+	text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// `lines` as one left-aligned DOT label.
+fn label(lines: &[String]) -> String {
+	lines.iter().map(|line| escape(line) + "\\l").collect()
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{Action, Condition, Cost, Goal, PlanOptions, Value};
+
+	fn domain() -> (FactMap, Planner, Goal) {
+		let mut facts = FactMap::new();
+		let has_key = facts.new_fact("has_key").unwrap();
+		let door = facts.new_fact("door").unwrap();
+		let mut take = Action::new("Take \"Key\"", Cost(1));
+		take.push_effect(Effect::Set(has_key, Value::TRUE));
+		let mut open = Action::new("Open", Cost(2));
+		open.push_precondition(Condition::Eq(has_key, Value::TRUE))
+			.push_effect(Effect::Set(door, Value::TRUE));
+		let mut planner = Planner::new();
+		planner.push_action(take);
+		planner.push_action(open);
+		let mut goal = Goal::new("Open door");
+		goal.push_condition(Condition::Eq(door, Value::TRUE));
+		(facts, planner, goal)
+	}
+
+	#[test]
+	fn action_graph() {
+		let (facts, planner, _) = domain();
+		assert_eq!(
+			facts.action_graph_dot(&planner),
+			"digraph actions {\n\
+			 \tnode [shape=box];\n\
+			 \ta0 [label=\"Take \\\"Key\\\"\"];\n\
+			 \ta1 [label=\"Open\"];\n\
+			 \ta0 -> a1 [label=\"has_key\"];\n\
+			 }\n"
+		);
+	}
+
+	#[test]
+	fn search_tree() {
+		let (facts, planner, goal) = domain();
+		let mut tree = SearchTree::new();
+		planner
+			.plan_traced(
+				&facts.new_world_state(),
+				&goal,
+				&PlanOptions::default(),
+				&mut tree,
+			)
+			.unwrap();
+		assert_eq!(
+			facts.search_tree_dot(&planner, &tree),
+			"digraph search {\n\
+			 \tnode [shape=box];\n\
+			 \tn0 [label=\"has_key = 0\\ldoor = 0\\l\"];\n\
+			 \tn1 [label=\"g = 1\\lhas_key = 1\\l\"];\n\
+			 \tn2 [label=\"g = 3\\lhas_key = 1\\ldoor = 1\\l\", peripheries=2, style=dashed];\n\
+			 \tn0 -> n1 [label=\"Take \\\"Key\\\"\"];\n\
+			 \tn1 -> n2 [label=\"Open\"];\n\
+			 }\n"
+		);
+	}
+}
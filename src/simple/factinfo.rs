@@ -0,0 +1,78 @@
+use crate::fact::FactRange;
+use crate::value::Value;
+
+/// The declared type of a fact. `Bool` facts hold `0` or `1`.
+#[derive(Clone, Copy, Debug, Default, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(rename_all = "lowercase"))]
+pub enum FactKind {
+	#[default]
+	Int,
+	Bool,
+}
+
+/// Metadata registered alongside a fact name in a `FactMap`.
+#[derive(Clone, Debug, Default, Eq, Hash, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FactInfo {
+	#[cfg_attr(feature = "serde", serde(default))]
+	pub kind: FactKind,
+	/// The value used by `FactMap::new_world_state`.
+	#[cfg_attr(feature = "serde", serde(default))]
+	pub default: i64,
+	#[cfg_attr(feature = "serde", serde(default))]
+	pub min: Option<i64>,
+	#[cfg_attr(feature = "serde", serde(default))]
+	pub max: Option<i64>,
+	#[cfg_attr(feature = "serde", serde(default))]
+	pub description: Option<String>,
+}
+
+impl FactInfo {
+	#[must_use]
+	pub fn int() -> Self {
+		Self::default()
+	}
+
+	#[must_use]
+	pub fn bool() -> Self {
+		Self {
+			kind: FactKind::Bool,
+			..Self::default()
+		}
+	}
+
+	#[must_use]
+	pub fn with_default(mut self, default: i64) -> Self {
+		self.default = default;
+		self
+	}
+
+	#[must_use]
+	pub fn with_range(mut self, min: i64, max: i64) -> Self {
+		self.min = Some(min);
+		self.max = Some(max);
+		self
+	}
+
+	#[must_use]
+	pub fn with_description(mut self, description: impl Into<String>) -> Self {
+		self.description = Some(description.into());
+		self
+	}
+
+	/// The effective range: `min` and `max`, narrowed to `0..=1` for `FactKind::Bool`.
+	#[must_use]
+	pub fn range(&self) -> FactRange {
+		let declared = FactRange::new(self.min.unwrap_or(i64::MIN), self.max.unwrap_or(i64::MAX));
+		match self.kind {
+			FactKind::Int => declared,
+			FactKind::Bool => declared.intersect(&FactRange::BOOL),
+		}
+	}
+
+	#[must_use]
+	pub fn default_value(&self) -> Value {
+		Value::Int(self.default)
+	}
+}
@@ -1,15 +1,28 @@
+use core::ops::Range;
+
 use hashbrown::HashMap;
 
-use crate::errors::{NewFactError, ParseConditionError, ParseEffectError};
-use crate::fact::FactId;
+use crate::errors::{
+	ExecutorError, FactRangeError, MergeFactsError, NewFactError, ParseConditionError,
+	ParseEffectError, ParseError, ParseErrorKind,
+};
+use crate::fact::{FactId, FactRange, FactRemap};
+use crate::simple::FactInfo;
+use crate::simple::parser::{self, Token, TokenKind};
 use crate::value::Value;
-use crate::{Condition, Effect};
+use crate::world::WorldState;
+use crate::{Condition, Effect, Executor, ExecutorArg};
+
+/// An operator token of a condition or effect string and its byte span.
+type Field<'a> = (&'a str, Range<usize>);
 
 #[derive(Clone, Debug, Eq, PartialEq)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct FactMap {
 	lut: Vec<String>,
 	map: HashMap<String, FactId>,
+	#[cfg_attr(feature = "serde", serde(default))]
+	info: Vec<FactInfo>, // indexed by FactId
 }
 
 impl FactMap {
@@ -18,39 +31,121 @@ impl FactMap {
 		FactMap {
 			lut: Vec::new(),
 			map: HashMap::new(),
+			info: Vec::new(),
 		}
 	}
 
+	/// Fact names follow the identifier grammar of the condition/effect parser, so every fact can be
+	/// referenced from a data file: `.` separated segments of Unicode letters, digits and `_`, each not
+	/// starting with a digit, such as `hunger` or `inventory.wood`. `true` and `false` are reserved.
+	///
 	/// # Errors
 	/// - `NewFactError::EmptyFactName`: if the provided fact name was empty
-	/// - `NewFactError::InitialDigitFactName`: if the provided fact name begins with a digit
+	/// - `NewFactError::ContainsAsciiWhitespaceFactName`: if the provided fact name contains any whitespace
+	/// - `NewFactError::InvalidCharacterFactName`: if the provided fact name contains anything but letters, digits, `_`, and `.`
+	/// - `NewFactError::EmptySegmentFactName`: if the provided fact name starts or ends with `.`, or contains `..`
+	/// - `NewFactError::InitialDigitFactName`: if the provided fact name (or a segment of it) begins with a digit
+	/// - `NewFactError::ReservedFactName`: if the provided fact name is a keyword
 	/// - `NewFactError::DuplicateFactName`: if the provided fact name was already used
-	/// - `NewFactError::ContainsAsciiWhitespaceFactName`: if the provided fact names contains ascii whitespace
 	/// - `NewFactError::OutOfFactIdSpace`: somehow you used 65k fact ids and want to keep going
 	pub fn new_fact(&mut self, fact_name: impl Into<String>) -> Result<FactId, NewFactError> {
+		self.new_fact_with(fact_name, FactInfo::default())
+	}
+
+	/// `new_fact` with a declared type, default value, range and description.
+	///
+	/// # Errors
+	/// - `NewFactError::DefaultOutOfRange`: if `info.default` is outside of `info.range()`
+	/// - Any error from `new_fact`
+	pub fn new_fact_with(
+		&mut self,
+		fact_name: impl Into<String>,
+		info: FactInfo,
+	) -> Result<FactId, NewFactError> {
 		let fact_name = fact_name.into();
-		let Some(ch0) = fact_name.as_bytes().first() else {
-			return Err(NewFactError::EmptyFactName);
-		};
-		let ch0 = char::from(*ch0);
-		if ch0.is_ascii_digit() {
-			return Err(NewFactError::InitialDigitFactName);
-		}
-		if fact_name.contains(' ') {
-			return Err(NewFactError::ContainsAsciiWhitespaceFactName);
-		}
+		parser::check_identifier(&fact_name)?;
 		if self.map.contains_key(&fact_name) {
 			return Err(NewFactError::DuplicateFactName);
 		}
+		if !info.range().contains(info.default) {
+			return Err(NewFactError::DefaultOutOfRange(info.default));
+		}
 		let Ok(next_id) = u16::try_from(self.lut.len()) else {
 			return Err(NewFactError::OutOfFactIdSpace);
 		};
 		let fact = FactId(next_id);
 		self.lut.push(fact_name.clone());
 		self.map.insert(fact_name, fact);
+		self.info.resize(self.lut.len() - 1, FactInfo::default());
+		self.info.push(info);
 		Ok(fact)
 	}
 
+	#[must_use]
+	pub fn get_fact_info(&self, fact: FactId) -> Option<&FactInfo> {
+		self.info.get(usize::from(fact.0))
+	}
+
+	/// Every fact's effective range, indexed by `FactId`, for `Planner::set_fact_ranges`.
+	#[must_use]
+	pub fn get_fact_ranges(&self) -> Vec<FactRange> {
+		self.info.iter().map(FactInfo::range).collect()
+	}
+
+	/// A `WorldState` holding every fact's default value.
+	#[must_use]
+	pub fn new_world_state(&self) -> WorldState {
+		let mut state = WorldState::new(self.lut.len());
+		for (index, info) in self.info.iter().enumerate() {
+			state.set(
+				FactId(u16::try_from(index).unwrap_or(u16::MAX)),
+				info.default_value(),
+			);
+		}
+		state
+	}
+
+	/// Check a condition that compares against a constant against the fact's range.
+	///
+	/// # Errors
+	/// - `FactRangeError::Unsatisfiable`: if no value in the fact's range satisfies the condition, e.g. `hp > 100` when `hp` is in `0..=100`
+	/// - `FactRangeError::OutOfRange`: if the constant is outside of the fact's range, e.g. `hp != -1`
+	pub fn check_condition(&self, condition: &Condition) -> Result<(), FactRangeError> {
+		let (Some(info), Value::Int(int)) =
+			(self.get_fact_info(condition.fact()), condition.value())
+		else {
+			return Ok(());
+		};
+		let FactRange { min, max } = info.range();
+		let satisfiable = match condition {
+			Condition::Eq(..) => min <= int && int <= max,
+			Condition::Ne(..) => !(min == int && max == int),
+			Condition::Gt(..) => max > int,
+			Condition::Lt(..) => min < int,
+			Condition::Ge(..) => max >= int,
+			Condition::Le(..) => min <= int,
+		};
+		let condition_text = self.display_condition(condition).to_string();
+		let fact = self.display_fact(condition.fact()).to_string();
+		if !satisfiable {
+			Err(FactRangeError::Unsatisfiable {
+				condition: condition_text,
+				fact,
+				min,
+				max,
+			})
+		} else if !info.range().contains(int) {
+			Err(FactRangeError::OutOfRange {
+				condition: condition_text,
+				fact,
+				min,
+				max,
+			})
+		} else {
+			Ok(())
+		}
+	}
+
 	#[must_use]
 	pub fn get_fact_id(&self, fact_name: impl Into<String>) -> Option<FactId> {
 		self.map.get(&fact_name.into()).copied()
@@ -66,96 +161,270 @@ impl FactMap {
 		&self.map
 	}
 
+	#[must_use]
+	pub fn len(&self) -> usize {
+		self.lut.len()
+	}
+
+	#[must_use]
+	pub fn is_empty(&self) -> bool {
+		self.lut.is_empty()
+	}
+
+	/// Every fact under `scope`, e.g. `"squad"` yields `squad.alert` and `squad.leader.hp` (with their full names).
+	pub fn get_scope_facts<'a>(
+		&'a self,
+		scope: &'a str,
+	) -> impl Iterator<Item = (FactId, &'a str)> + 'a {
+		self.lut.iter().filter_map(move |name| {
+			let rest = name.strip_prefix(scope)?.strip_prefix('.')?;
+			(!rest.is_empty()).then(|| (self.map[name], name.as_str()))
+		})
+	}
+
+	/// A copy of this map with every name prefixed by `scope.`, keeping the same ids, so `hp` becomes `self.hp`.
+	///
+	/// # Errors
+	/// - `MergeFactsError::InvalidScope`: if `scope` is not a valid fact name itself
+	pub fn scoped(&self, scope: &str) -> Result<FactMap, MergeFactsError> {
+		parser::check_identifier(scope).map_err(MergeFactsError::InvalidScope)?;
+		let mut scoped = FactMap::new();
+		for (index, name) in self.lut.iter().enumerate() {
+			let name = format!("{scope}.{name}");
+			let info = self.info.get(index).cloned().unwrap_or_default();
+			let _ = scoped
+				.new_fact_with(name, info)
+				.map_err(MergeFactsError::InvalidScope)?;
+		}
+		Ok(scoped)
+	}
+
+	/// Names defined in both this map and `other`.
+	#[must_use]
+	pub fn collisions(&self, other: &FactMap) -> Vec<String> {
+		other
+			.lut
+			.iter()
+			.filter(|name| self.map.contains_key(*name))
+			.cloned()
+			.collect()
+	}
+
+	/// Append every fact of `other` to this map, returning how `other`'s ids map into this map's id space.
+	/// Pass the remap to `remapped` (or `Planner::remap_facts`, `WorldState::merge_from`) to bring `other`'s
+	/// conditions, effects, actions and states along. Nothing is added if an error is returned.
+	///
+	/// # Errors
+	/// - `MergeFactsError::Collision`: if any name exists in both maps (see `collisions`); use `merge_scoped` to keep both
+	/// - `MergeFactsError::OutOfFactIdSpace`: if the merged map would need more than 65k fact ids
+	pub fn merge(&mut self, other: &FactMap) -> Result<FactRemap, MergeFactsError> {
+		let collisions = self.collisions(other);
+		if !collisions.is_empty() {
+			return Err(MergeFactsError::Collision(collisions));
+		}
+		if self.lut.len() + other.lut.len() > usize::from(u16::MAX) + 1 {
+			return Err(MergeFactsError::OutOfFactIdSpace);
+		}
+		let mut map = Vec::with_capacity(other.lut.len());
+		self.info.resize(self.lut.len(), FactInfo::default());
+		for (index, name) in other.lut.iter().enumerate() {
+			let fact = FactId(u16::try_from(self.lut.len()).unwrap_or(u16::MAX));
+			self.lut.push(name.clone());
+			self.map.insert(name.clone(), fact);
+			self.info
+				.push(other.info.get(index).cloned().unwrap_or_default());
+			map.push(fact);
+		}
+		Ok(FactRemap::new(map))
+	}
+
+	/// `merge` with every name of `other` prefixed by `scope.`, e.g. to combine `self`, `squad`, and `world` facts.
+	///
+	/// # Errors
+	/// - `MergeFactsError::InvalidScope`: if `scope` is not a valid fact name itself
+	/// - Any error from `merge`
+	pub fn merge_scoped(
+		&mut self,
+		scope: &str,
+		other: &FactMap,
+	) -> Result<FactRemap, MergeFactsError> {
+		self.merge(&other.scoped(scope)?)
+	}
+
 	/// Parse a value string such as:
 	/// `"3"` => `Value::Int(3)`
+	/// `"-3"` => `Value::Int(-3)`
+	/// `"true"` => `Value::TRUE`
 	/// `"my_fact"` => `Value::Ref(my_fact)`
 	/// `"123 invalid"` => `Value::Error`
 	#[must_use]
 	pub fn parse_value(&self, string: impl Into<String>) -> Value {
 		let string = string.into();
-		match string.parse::<i64>() {
-			Ok(int) => Value::Int(int),
-			Err(_) => {
-				if let Some(fact_id) = self.get_fact_id(string) {
-					Value::Ref(fact_id)
-				} else {
-					Value::Error
-				}
-			}
+		match parser::tokenize(&string).as_deref() {
+			Ok([token]) => self.resolve_value(token).unwrap_or(Value::Error),
+			_ => Value::Error,
 		}
 	}
 
+	fn resolve_value(&self, token: &Token) -> Option<Value> {
+		match token.kind {
+			TokenKind::Int(int) => Some(Value::Int(int)),
+			TokenKind::Ident("true") => Some(Value::TRUE),
+			TokenKind::Ident("false") => Some(Value::FALSE),
+			TokenKind::Ident(name) => self.get_fact_id(name).map(Value::Ref),
+			TokenKind::Op(_) => None,
+		}
+	}
+
+	/// Find the fact name closest to `fact_name`, for "did you mean" hints.
+	/// Only names within a small edit distance (about a third of the name's length) are suggested.
+	#[must_use]
+	pub fn suggest(&self, fact_name: &str) -> Option<&str> {
+		let limit = (fact_name.chars().count() / 3).max(1);
+		self.lut
+			.iter()
+			.map(|name| (edit_distance(fact_name, name), name))
+			.filter(|(distance, _)| *distance <= limit)
+			.min_by_key(|(distance, _)| *distance)
+			.map(|(_, name)| name.as_str())
+	}
+
 	/// Parse a condition string such as:
 	/// `"my_fact == 3"` => `Condition::Eq(my_fact, Value::Int(3))`
-	/// `"my_fact > my_other_fact"` => `Condition::Eq(my_fact, Value::Ref(my_other_fact))`
+	/// `"my_fact>my_other_fact"` => `Condition::Gt(my_fact, Value::Ref(my_other_fact))`
+	/// `"is_threatened == false // calm"` => `Condition::Eq(is_threatened, Value::FALSE)`
 	///
 	/// # Errors
-	/// - `ParseConditionError::WrongFieldCount` if the string does not split into exactly `3` fields (ascii spacing as separators)
-	/// - `ParseConditionError::LhsNotAFactId` if the lhs is not a fact id
-	/// - `ParseConditionError::RhsNotAFactNameOrId` if parsing the rhs with `parse_value` returns `Value::Error` (it wasn't an `i64` or fact id)
-	/// - `ParseConditionError::UnrecognizedOperator` if the middle field is not one of `"=="`, `"!="`, `"<"`, `">"`, `"<="`, or `">="`
+	/// Returns a `ParseError` pointing at the offending field, with one of these kinds:
+	/// - `ParseErrorKind::UnexpectedEnd`, `UnexpectedToken`, `UnexpectedCharacter`, or `InvalidInteger` if the string is not
+	///   exactly `lhs op rhs` (see the `parser` module for the token syntax)
+	/// - `ParseErrorKind::LhsNotAFactId` if the lhs is not a fact id
+	/// - `ParseErrorKind::RhsNotAFactNameOrId` if the rhs is a name but not a fact name or keyword
+	/// - `ParseErrorKind::UnrecognizedOperator` if the middle field is not one of `"=="`, `"!="`, `"<"`, `">"`, `"<="`, or `">="`
 	pub fn parse_condition(
 		&self,
 		string: impl Into<String>,
 	) -> Result<Condition, ParseConditionError> {
 		let string = string.into();
-		let fields = string
-			.as_str()
-			.split_ascii_whitespace()
-			.collect::<Vec<&str>>();
-		if fields.len() != 3 {
-			return Err(ParseConditionError::WrongFieldCount);
-		}
-		let Some(lhs) = self.get_fact_id(fields[0]) else {
-			return Err(ParseConditionError::LhsNotAFactId);
-		};
-		let rhs = self.parse_value(fields[2]);
-		if rhs.is_error() {
-			return Err(ParseConditionError::RhsNotAFactNameOrId);
-		}
-		match fields[1] {
+		let (lhs, op, rhs) = self.parse_fields(&string)?;
+		match op.0 {
 			"==" => Ok(Condition::Eq(lhs, rhs)),
 			"!=" => Ok(Condition::Ne(lhs, rhs)),
 			">" => Ok(Condition::Gt(lhs, rhs)),
 			"<" => Ok(Condition::Lt(lhs, rhs)),
 			">=" => Ok(Condition::Ge(lhs, rhs)),
 			"<=" => Ok(Condition::Le(lhs, rhs)),
-			_ => Err(ParseConditionError::UnrecognizedOperator),
+			_ => {
+				let suggestion = match op.0 {
+					"=" => Some("=="),
+					"=>" => Some(">="),
+					"=<" => Some("<="),
+					"<>" | "=!" => Some("!="),
+					_ => None,
+				};
+				Err(ParseError::new(
+					ParseErrorKind::UnrecognizedOperator(op.0.into()),
+					string.as_str(),
+					op.1.clone(),
+				)
+				.with_suggestion(suggestion))
+			}
 		}
 	}
 
 	/// Parse an effect string such as:
 	/// `"my_fact = 3"` => `Effect::Set(my_fact, Value::Int(3))`
 	/// `"my_fact += my_other_fact"` => `Effect::Add(my_fact, Value::Ref(my_other_fact))`
+	/// `"gold -=-2"` => `Effect::Sub(gold, Value::Int(-2))`
 	///
 	/// # Errors
-	/// - `ParseEffectError::WrongFieldCount` if the string does not split into exactly `3` fields (ascii spacing as separators)
-	/// - `ParseEffectError::LhsNotAFactId` if the lhs is not a fact id
-	/// - `ParseEffectError::RhsNotAFactNameOrId` if parsing the rhs with `parse_value` returns `Value::Error` (it wasn't an `i64` or fact id)
-	/// - `ParseEffectError::UnrecognizedOperator` if the middle field is not one of `"="`, `"+="`, or `"-="`
+	/// Returns a `ParseError` pointing at the offending field, with one of these kinds:
+	/// - `ParseErrorKind::UnexpectedEnd`, `UnexpectedToken`, `UnexpectedCharacter`, or `InvalidInteger` if the string is not
+	///   exactly `lhs op rhs` (see the `parser` module for the token syntax)
+	/// - `ParseErrorKind::LhsNotAFactId` if the lhs is not a fact id
+	/// - `ParseErrorKind::RhsNotAFactNameOrId` if the rhs is a name but not a fact name or keyword
+	/// - `ParseErrorKind::UnrecognizedOperator` if the middle field is not one of `"="`, `"+="`, or `"-="`
 	pub fn parse_effect(&self, string: impl Into<String>) -> Result<Effect, ParseEffectError> {
 		let string = string.into();
-		let fields = string
-			.as_str()
-			.split_ascii_whitespace()
-			.collect::<Vec<&str>>();
-		if fields.len() != 3 {
-			return Err(ParseEffectError::WrongFieldCount);
-		}
-		let Some(lhs) = self.get_fact_id(fields[0]) else {
-			return Err(ParseEffectError::LhsNotAFactId);
-		};
-		let rhs = self.parse_value(fields[2]);
-		if rhs.is_error() {
-			return Err(ParseEffectError::RhsNotAFactNameOrId);
-		}
-		match fields[1] {
+		let (lhs, op, rhs) = self.parse_fields(&string)?;
+		match op.0 {
 			"=" => Ok(Effect::Set(lhs, rhs)),
 			"+=" => Ok(Effect::Add(lhs, rhs)),
 			"-=" => Ok(Effect::Sub(lhs, rhs)),
-			_ => Err(ParseEffectError::UnrecognizedOperator),
+			_ => {
+				let suggestion = match op.0 {
+					"==" | ":=" => Some("="),
+					"=+" => Some("+="),
+					"=-" => Some("-="),
+					_ => None,
+				};
+				Err(ParseError::new(
+					ParseErrorKind::UnrecognizedOperator(op.0.into()),
+					string.as_str(),
+					op.1.clone(),
+				)
+				.with_suggestion(suggestion))
+			}
 		}
 	}
+
+	/// Parse `lhs op rhs` and resolve both operands, leaving the operator to the caller.
+	fn parse_fields<'a>(&self, string: &'a str) -> Result<(FactId, Field<'a>, Value), ParseError> {
+		let statement = parser::parse_statement(string)?;
+		let (lhs_name, lhs_span) = statement.lhs;
+		let Some(lhs) = self.get_fact_id(lhs_name) else {
+			return Err(ParseError::new(
+				ParseErrorKind::LhsNotAFactId(lhs_name.into()),
+				string,
+				lhs_span,
+			)
+			.with_suggestion(self.suggest(lhs_name)));
+		};
+		let Some(rhs) = self.resolve_value(&statement.rhs) else {
+			let rhs_name = &string[statement.rhs.span.clone()];
+			return Err(ParseError::new(
+				ParseErrorKind::RhsNotAFactNameOrId(rhs_name.into()),
+				string,
+				statement.rhs.span.clone(),
+			)
+			.with_suggestion(self.suggest(rhs_name)));
+		};
+		Ok((lhs, statement.op, rhs))
+	}
+
+	/// Parse an executor from its kind and a `:` separated argument string, such as:
+	/// `("locate_passable", "4:8")` => `locate_passable` with `[Int(4), Int(8)]`
+	/// `("recall_located", "$escape")` => `recall_located` with `[Param("escape")]`
+	/// `("watch", "@can_see_threat")` => `watch` with `[Fact(can_see_threat)]`
+	///
+	/// Facts can also be given by id, as `@#2`, which is how `Executor`'s `Display` writes them. See
+	/// `ExecutorArg::parse_list` for escaping `:` and text that would otherwise parse differently.
+	///
+	/// # Errors
+	/// - `ExecutorError::UnknownFact` if an `@` argument does not name a fact in this map
+	/// - Any error from `Executor::validate`
+	pub fn parse_executor(
+		&self,
+		kind: impl Into<String>,
+		args: impl Into<String>,
+	) -> Result<Executor, ExecutorError> {
+		let mut executor = Executor::new(kind);
+		for (arg, escaped) in ExecutorArg::parse_list_escaped(&args.into()) {
+			let arg = match arg {
+				ExecutorArg::Text(text) if !escaped && text.trim().starts_with('@') => {
+					let name = &text.trim()[1..];
+					let Some(fact_id) = self.get_fact_id(name) else {
+						return Err(ExecutorError::UnknownFact(name.into()));
+					};
+					ExecutorArg::Fact(fact_id)
+				}
+				arg => arg,
+			};
+			executor.push_arg(arg);
+		}
+		executor.validate(self.lut.len())?;
+		Ok(executor)
+	}
 }
 
 impl Default for FactMap {
@@ -163,3 +432,178 @@ impl Default for FactMap {
 		Self::new()
 	}
 }
+
+/// Levenshtein distance over `char`s.
+fn edit_distance(a: &str, b: &str) -> usize {
+	let b = b.chars().collect::<Vec<char>>();
+	let mut row = (0..=b.len()).collect::<Vec<usize>>();
+	for (i, ca) in a.chars().enumerate() {
+		let mut diagonal = row[0];
+		row[0] = i + 1;
+		for (j, cb) in b.iter().enumerate() {
+			let above = row[j + 1];
+			row[j + 1] = if ca == *cb {
+				diagonal
+			} else {
+				1 + diagonal.min(above).min(row[j])
+			};
+			diagonal = above;
+		}
+	}
+	row[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::Action;
+
+	fn deer_facts() -> FactMap {
+		let mut facts = FactMap::new();
+		facts.new_fact("hunger").unwrap();
+		facts.new_fact("satiety").unwrap();
+		facts
+	}
+
+	#[test]
+	fn parse_error_points_at_unknown_fact() {
+		let facts = deer_facts();
+		let err = facts.parse_condition("hungr >= 3").unwrap_err();
+		assert_eq!(err.kind, ParseErrorKind::LhsNotAFactId("hungr".into()));
+		assert_eq!(err.span, 0..5);
+		assert_eq!(err.suggestion.as_deref(), Some("hunger"));
+		assert_eq!(
+			err.render(),
+			"error: lhs `hungr` is not a fact name\n  | hungr >= 3\n  | ^^^^^\n  = help: did you mean `hunger`?"
+		);
+
+		let err = facts.parse_effect("hunger -= satiety_").unwrap_err();
+		assert_eq!(err.snippet(), "satiety_");
+		assert_eq!(err.suggestion.as_deref(), Some("satiety"));
+	}
+
+	#[test]
+	fn parse_error_suggests_operator() {
+		let facts = deer_facts();
+		let err = facts.parse_condition("hunger = 3").unwrap_err();
+		assert_eq!(err.span, 7..8);
+		assert_eq!(err.suggestion.as_deref(), Some("=="));
+	}
+
+	#[test]
+	fn parses_without_spacing() {
+		let facts = deer_facts();
+		let hunger = facts.get_fact_id("hunger").unwrap();
+		let satiety = facts.get_fact_id("satiety").unwrap();
+		assert!(matches!(
+			facts.parse_condition("hunger>=-3"),
+			Ok(Condition::Ge(f, Value::Int(-3))) if f == hunger
+		));
+		assert!(matches!(
+			facts.parse_condition("hunger!=true // comment"),
+			Ok(Condition::Ne(f, Value::Int(1))) if f == hunger
+		));
+		assert!(matches!(
+			facts.parse_effect("satiety -=hunger"),
+			Ok(Effect::Sub(f, Value::Ref(r))) if f == satiety && r == hunger
+		));
+		assert!(facts.parse_value("123 invalid").is_error());
+	}
+
+	#[test]
+	fn executor_display_parses_back() {
+		let facts = deer_facts();
+		let satiety = facts.get_fact_id("satiety").unwrap();
+		let executor = facts
+			.parse_executor("say", r"@satiety:\@hunger:12:\12:  spaced :a\:b\\c:$target")
+			.unwrap();
+		assert_eq!(
+			executor.args,
+			vec![
+				ExecutorArg::Fact(satiety),
+				ExecutorArg::Text("@hunger".into()),
+				ExecutorArg::Int(12),
+				ExecutorArg::Text("12".into()),
+				ExecutorArg::Text("  spaced ".into()),
+				ExecutorArg::Text(r"a:b\c".into()),
+				ExecutorArg::Param("target".into()),
+			]
+		);
+		let written = executor.to_string();
+		let (kind, args) = written.split_once(' ').unwrap();
+		assert_eq!(facts.parse_executor(kind, args).unwrap(), executor);
+		assert_eq!(Executor::from((kind, args)), executor);
+	}
+
+	#[test]
+	fn new_fact_errors() {
+		let mut facts = deer_facts();
+		for (name, expected) in [
+			("", NewFactError::EmptyFactName),
+			("2hp", NewFactError::InitialDigitFactName),
+			("inventory.2wood", NewFactError::InitialDigitFactName),
+			("max hp", NewFactError::ContainsAsciiWhitespaceFactName),
+			("max\thp", NewFactError::ContainsAsciiWhitespaceFactName),
+			("max\u{a0}hp", NewFactError::ContainsAsciiWhitespaceFactName),
+			("a==b", NewFactError::InvalidCharacterFactName('=')),
+			("+=", NewFactError::InvalidCharacterFactName('+')),
+			("inventory..wood", NewFactError::EmptySegmentFactName),
+			(".wood", NewFactError::EmptySegmentFactName),
+			("true", NewFactError::ReservedFactName("true".into())),
+			("hunger", NewFactError::DuplicateFactName),
+		] {
+			let err = facts.new_fact(name).unwrap_err();
+			assert_eq!(err.to_string(), expected.to_string(), "{name:?}");
+		}
+		assert!(facts.new_fact("inventory.wood").is_ok());
+		assert!(facts.new_fact("größe").is_ok());
+		assert!(facts.new_fact("_true").is_ok());
+		assert!(facts.parse_condition("inventory.wood>größe").is_ok());
+	}
+
+	#[test]
+	fn new_fact_out_of_id_space() {
+		let mut facts = FactMap::new();
+		for i in 0..=u16::MAX {
+			facts.new_fact(format!("f{i}")).unwrap();
+		}
+		assert!(matches!(
+			facts.new_fact("one_too_many"),
+			Err(NewFactError::OutOfFactIdSpace)
+		));
+	}
+
+	#[test]
+	fn merge_scoped_maps() {
+		let mut agent = deer_facts();
+		let mut squad = FactMap::new();
+		let alert = squad.new_fact("alert").unwrap();
+		let leader_hp = squad.new_fact("leader_hp").unwrap();
+
+		let mut mood = Action::new("Panic", crate::Cost(1));
+		mood.push_precondition(Condition::Eq(alert, Value::TRUE))
+			.push_effect(Effect::Set(alert, Value::Ref(leader_hp)));
+
+		let remap = agent.merge_scoped("squad", &squad).unwrap();
+		let alert = agent.get_fact_id("squad.alert").unwrap();
+		let leader_hp = agent.get_fact_id("squad.leader_hp").unwrap();
+		assert_eq!(alert, FactId(2));
+		assert_eq!(
+			agent.get_scope_facts("squad").collect::<Vec<_>>(),
+			[(alert, "squad.alert"), (leader_hp, "squad.leader_hp")]
+		);
+
+		let mood = mood.remapped(&remap);
+		assert!(matches!(mood.preconditions[0], Condition::Eq(f, _) if f == alert));
+		assert!(matches!(
+			mood.effects[0],
+			Effect::Set(f, Value::Ref(r)) if f == alert && r == leader_hp
+		));
+
+		assert!(matches!(
+			agent.merge_scoped("squad", &squad),
+			Err(MergeFactsError::Collision(names)) if names == ["squad.alert", "squad.leader_hp"]
+		));
+		assert_eq!(agent.len(), 4);
+	}
+}
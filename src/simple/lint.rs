@@ -0,0 +1,414 @@
+//! Static checks of an action library, for catching design mistakes before shipping a domain.
+
+use core::fmt::{self, Display, Formatter};
+
+use crate::simple::FactMap;
+use crate::{Action, ActionId, Condition, Effect, FactId, FactRange, Goal, Planner, Value};
+
+/// Returned by `Planner::lint`.
+#[derive(Clone, Debug, Default)]
+pub struct LintReport {
+	pub diagnostics: Vec<Diagnostic>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Diagnostic {
+	pub severity: Severity,
+	pub lint: Lint,
+	/// A readable description using the `FactMap`'s names.
+	pub message: String,
+}
+
+#[derive(Clone, Copy, Debug, Eq, Ord, PartialEq, PartialOrd)]
+pub enum Severity {
+	Warning,
+	Error,
+}
+
+/// What a `Diagnostic` is about.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Lint {
+	/// The preconditions on `fact` can never all hold, e.g. `x == 1` and `x == 2`.
+	UnsatisfiablePreconditions { action: ActionId, fact: FactId },
+	/// The action's cost is negative, which A* can't handle.
+	NegativeCost { action: ActionId },
+	/// The action changes nothing.
+	NoEffects { action: ActionId },
+	/// An effect never changes its fact, e.g. `x += 0`, or `x = 1` when a precondition requires `x == 1`.
+	NoOpEffect { action: ActionId, effect: usize },
+	/// More than one action has this name.
+	DuplicateActionName {
+		name: String,
+		actions: Vec<ActionId>,
+	},
+	/// A goal condition's fact is not written by any action, so only the start state can satisfy it.
+	UnwrittenGoalFact { goal: String, fact: FactId },
+	/// These zero-cost actions enable each other, so search can explore them endlessly at no cost.
+	ZeroCostCycle { actions: Vec<ActionId> },
+}
+
+impl LintReport {
+	fn push(&mut self, severity: Severity, lint: Lint, message: String) {
+		self.diagnostics.push(Diagnostic {
+			severity,
+			lint,
+			message,
+		});
+	}
+
+	#[must_use]
+	pub fn has_errors(&self) -> bool {
+		self.diagnostics
+			.iter()
+			.any(|diagnostic| diagnostic.severity == Severity::Error)
+	}
+
+	#[must_use]
+	pub fn is_empty(&self) -> bool {
+		self.diagnostics.is_empty()
+	}
+}
+
+impl Display for Severity {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		match self {
+			Severity::Warning => write!(f, "warning"),
+			Severity::Error => write!(f, "error"),
+		}
+	}
+}
+
+impl Display for Diagnostic {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		write!(f, "{}: {}", self.severity, self.message)
+	}
+}
+
+impl Display for LintReport {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		for diagnostic in &self.diagnostics {
+			writeln!(f, "{diagnostic}")?;
+		}
+		Ok(())
+	}
+}
+
+impl Planner {
+	/// Check the action library (and the goals it should reach) for likely design mistakes; see `Lint`.
+	/// Fail CI on `LintReport::has_errors`, or on any diagnostic at all.
+	#[must_use]
+	pub fn lint(&self, facts: &FactMap, goals: &[Goal]) -> LintReport {
+		let mut report = LintReport::default();
+		self.lint_actions(facts, &mut report);
+		self.lint_names(&mut report);
+		self.lint_goals(facts, goals, &mut report);
+		self.lint_cycles(&mut report);
+		report
+	}
+
+	fn lint_actions(&self, facts: &FactMap, report: &mut LintReport) {
+		let ranges = facts.get_fact_ranges();
+		for (id, action) in self.actions() {
+			let name = &action.name;
+			if let Some(fact) = unsatisfiable_fact(action, &ranges) {
+				report.push(
+					Severity::Error,
+					Lint::UnsatisfiablePreconditions { action: id, fact },
+					format!(
+						"action \"{name}\" can never run: its preconditions on {} contradict each other",
+						facts.display_fact(fact)
+					),
+				);
+			}
+			if action.cost.0 < 0 {
+				report.push(
+					Severity::Error,
+					Lint::NegativeCost { action: id },
+					format!("action \"{name}\" has negative cost {}", action.cost.0),
+				);
+			}
+			if action.effects.is_empty() {
+				report.push(
+					Severity::Warning,
+					Lint::NoEffects { action: id },
+					format!("action \"{name}\" has no effects"),
+				);
+			}
+			for (effect_index, effect) in action.effects.iter().enumerate() {
+				if is_no_op(action, effect) {
+					report.push(
+						Severity::Warning,
+						Lint::NoOpEffect {
+							action: id,
+							effect: effect_index,
+						},
+						format!(
+							"effect `{}` of action \"{name}\" never changes anything",
+							facts.display_effect(effect)
+						),
+					);
+				}
+			}
+		}
+	}
+
+	fn lint_names(&self, report: &mut LintReport) {
+		for (id, action) in self.actions() {
+			let same: Vec<ActionId> = self
+				.actions()
+				.filter(|(_, other)| other.name == action.name)
+				.map(|(other, _)| other)
+				.collect();
+			if same.len() > 1 && same[0] == id {
+				report.push(
+					Severity::Error,
+					Lint::DuplicateActionName {
+						name: action.name.clone(),
+						actions: same,
+					},
+					format!("action name \"{}\" is used more than once", action.name),
+				);
+			}
+		}
+	}
+
+	fn lint_goals(&self, facts: &FactMap, goals: &[Goal], report: &mut LintReport) {
+		for goal in goals {
+			let mut facts_seen: Vec<FactId> = Vec::new();
+			for condition in &goal.conditions {
+				let fact = condition.fact();
+				let written = self
+					.actions()
+					.any(|(_, action)| action.effects.iter().any(|effect| effect.fact() == fact));
+				if !written && !facts_seen.contains(&fact) {
+					facts_seen.push(fact);
+					report.push(
+						Severity::Warning,
+						Lint::UnwrittenGoalFact {
+							goal: goal.name.clone(),
+							fact,
+						},
+						format!(
+							"goal \"{}\" requires {}, which no action writes",
+							goal.name,
+							facts.display_fact(fact)
+						),
+					);
+				}
+			}
+		}
+	}
+
+	fn lint_cycles(&self, report: &mut LintReport) {
+		let actions: Vec<(ActionId, &Action)> = self.actions().collect();
+		for cycle in zero_cost_cycles(&actions) {
+			let names: Vec<&str> = cycle
+				.iter()
+				.filter_map(|id| Some(self.get_action(*id)?.name.as_str()))
+				.collect();
+			report.push(
+				Severity::Warning,
+				Lint::ZeroCostCycle { actions: cycle },
+				format!(
+					"zero-cost actions enable each other in a cycle: \"{}\"",
+					names.join("\", \"")
+				),
+			);
+		}
+	}
+}
+
+/// The first fact whose constant preconditions can't all hold within its range.
+fn unsatisfiable_fact(action: &Action, ranges: &[FactRange]) -> Option<FactId> {
+	let mut facts: Vec<FactId> = action.preconditions.iter().map(Condition::fact).collect();
+	facts.sort_unstable();
+	facts.dedup();
+	facts.into_iter().find(|fact| {
+		let mut range = ranges
+			.get(usize::from(fact.0))
+			.copied()
+			.unwrap_or(FactRange::UNBOUNDED);
+		let mut excluded = Vec::new();
+		for condition in &action.preconditions {
+			let (Value::Int(int), true) = (condition.value(), condition.fact() == *fact) else {
+				continue;
+			};
+			let allowed = match condition {
+				Condition::Eq(..) => FactRange::new(int, int),
+				Condition::Gt(..) => match int.checked_add(1) {
+					Some(min) => FactRange::new(min, i64::MAX),
+					None => return true,
+				},
+				Condition::Lt(..) => match int.checked_sub(1) {
+					Some(max) => FactRange::new(i64::MIN, max),
+					None => return true,
+				},
+				Condition::Ge(..) => FactRange::new(int, i64::MAX),
+				Condition::Le(..) => FactRange::new(i64::MIN, int),
+				Condition::Ne(..) => {
+					excluded.push(int);
+					continue;
+				}
+			};
+			range = range.intersect(&allowed);
+		}
+		if range.min > range.max {
+			return true;
+		}
+		excluded.sort_unstable();
+		excluded.dedup();
+		let width = i128::from(range.max) - i128::from(range.min) + 1;
+		let excluded_in_range = excluded.iter().filter(|int| range.contains(**int)).count();
+		i128::try_from(excluded_in_range).is_ok_and(|count| count >= width)
+	})
+}
+
+fn is_no_op(action: &Action, effect: &Effect) -> bool {
+	match *effect {
+		Effect::Add(_, Value::Int(0)) | Effect::Sub(_, Value::Int(0)) => true,
+		Effect::Set(fact, Value::Ref(other)) => fact == other,
+		Effect::Set(fact, Value::Int(int)) => action.preconditions.iter().any(|condition| {
+			matches!(*condition, Condition::Eq(required, Value::Int(value)) if required == fact && value == int)
+		}),
+		_ => false,
+	}
+}
+
+/// Groups of zero-cost actions that can enable each other in a loop: `a` enables `b` if one of `a`'s
+/// effects writes a fact read by one of `b`'s preconditions, or `b` has no preconditions at all.
+/// An action only loops on its own if it can keep changing a fact with `+=` or `-=`.
+fn zero_cost_cycles(actions: &[(ActionId, &Action)]) -> Vec<Vec<ActionId>> {
+	let free: Vec<(ActionId, &Action)> = actions
+		.iter()
+		.filter(|(_, action)| action.cost.0 <= 0 && !action.effects.is_empty())
+		.copied()
+		.collect();
+	let enables = |from: &Action, to: &Action| {
+		to.preconditions.is_empty()
+			|| from.effects.iter().any(|effect| {
+				to.preconditions.iter().any(|condition| {
+					condition
+						.referenced_facts()
+						.any(|fact| fact == effect.fact())
+				})
+			})
+	};
+	// reaches[i][j]: free[j] can run after free[i], transitively
+	let n = free.len();
+	let mut reaches: Vec<Vec<bool>> = free
+		.iter()
+		.map(|from| {
+			free.iter()
+				.map(|to| {
+					let ((from_id, from), (to_id, to)) = (*from, *to);
+					if from_id == to_id {
+						enables(from, to)
+							&& from
+								.effects
+								.iter()
+								.any(|effect| !matches!(effect, Effect::Set(..)))
+					} else {
+						enables(from, to)
+					}
+				})
+				.collect()
+		})
+		.collect();
+	for k in 0..n {
+		for i in 0..n {
+			if reaches[i][k] {
+				let via = reaches[k].clone();
+				for (reach, through) in reaches[i].iter_mut().zip(via) {
+					*reach |= through;
+				}
+			}
+		}
+	}
+	let mut assigned = vec![false; n];
+	let mut cycles = Vec::new();
+	for i in 0..n {
+		if assigned[i] || !reaches[i][i] {
+			continue;
+		}
+		let cycle: Vec<usize> = (i..n)
+			.filter(|j| reaches[i][*j] && reaches[*j][i])
+			.collect();
+		for j in &cycle {
+			assigned[*j] = true;
+		}
+		cycles.push(cycle.into_iter().map(|j| free[j].0).collect());
+	}
+	cycles
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+	use crate::{Cost, FactInfo};
+
+	#[test]
+	fn reports_every_lint() {
+		let mut facts = FactMap::new();
+		let x = facts.new_fact("x").unwrap();
+		let door = facts.new_fact_with("door", FactInfo::bool()).unwrap();
+		let gold = facts.new_fact("gold").unwrap();
+		let mut planner = Planner::new();
+
+		let mut contradiction = Action::new("Contradiction", Cost(1));
+		contradiction
+			.push_precondition(Condition::Eq(x, Value::Int(1)))
+			.push_precondition(Condition::Eq(x, Value::Int(2)))
+			.push_effect(Effect::Add(x, Value::Int(1)));
+		planner.push_action(contradiction);
+		let mut neither = Action::new("Neither", Cost(1));
+		neither
+			.push_precondition(Condition::Ne(door, Value::FALSE))
+			.push_precondition(Condition::Ne(door, Value::TRUE))
+			.push_effect(Effect::Sub(x, Value::Int(0)));
+		planner.push_action(neither);
+		let mut open = Action::new("Door", Cost(0));
+		open.push_precondition(Condition::Eq(door, Value::FALSE))
+			.push_effect(Effect::Set(door, Value::TRUE));
+		planner.push_action(open);
+		let mut close = Action::new("Door", Cost(0));
+		close
+			.push_precondition(Condition::Eq(door, Value::TRUE))
+			.push_effect(Effect::Set(door, Value::FALSE));
+		planner.push_action(close);
+		let mut idle = Action::new("Idle", Cost(-1));
+		idle.push_precondition(Condition::Ge(x, Value::Int(0)));
+		planner.push_action(idle);
+		let mut fine = Action::new("Fine", Cost(0));
+		fine.push_effect(Effect::Set(x, Value::Int(0)));
+		planner.push_action(fine);
+
+		let mut goal = Goal::new("Rich");
+		goal.push_condition(Condition::Ge(gold, Value::Int(10)))
+			.push_condition(Condition::Le(gold, Value::Int(20)));
+		let report = planner.lint(&facts, &[goal]);
+		assert!(report.has_errors());
+		assert_eq!(
+			report.to_string(),
+			"error: action \"Contradiction\" can never run: its preconditions on x contradict each other\n\
+			 error: action \"Neither\" can never run: its preconditions on door contradict each other\n\
+			 warning: effect `x -= 0` of action \"Neither\" never changes anything\n\
+			 error: action \"Idle\" has negative cost -1\n\
+			 warning: action \"Idle\" has no effects\n\
+			 error: action name \"Door\" is used more than once\n\
+			 warning: goal \"Rich\" requires gold, which no action writes\n\
+			 warning: zero-cost actions enable each other in a cycle: \"Door\", \"Door\"\n"
+		);
+		assert_eq!(
+			report.diagnostics[5].lint,
+			Lint::DuplicateActionName {
+				name: "Door".into(),
+				actions: vec![ActionId(2), ActionId(3)]
+			}
+		);
+
+		let mut clean = Planner::new();
+		let id = clean.push_action(planner.get_action(ActionId(0)).unwrap().clone());
+		clean.get_action_mut(id).unwrap().preconditions.pop();
+		assert!(clean.lint(&facts, &[]).is_empty());
+	}
+}
@@ -1,3 +1,23 @@
+mod display;
+#[cfg(feature = "serde")]
+mod domain;
+mod dot;
+mod factinfo;
 mod factmap;
+mod lint;
+mod parser;
+mod view;
 
+pub use display::{
+	DisplayAction, DisplayChange, DisplayChanges, DisplayCondition, DisplayEffect,
+	DisplayExplanation, DisplayFact, DisplayValue,
+};
+#[cfg(feature = "serde")]
+pub use domain::{
+	ActionSchema, CostSchema, Domain, DomainSchema, ExecutorSchema, FactSchema, GoalSchema,
+	LiteralSchema,
+};
+pub use factinfo::{FactInfo, FactKind};
 pub use factmap::FactMap;
+pub use lint::{Diagnostic, Lint, LintReport, Severity};
+pub use view::{FactView, FactViewMut};
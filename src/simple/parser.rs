@@ -0,0 +1,278 @@
+//! Tokenizer and parser for the `lhs op rhs` syntax of conditions and effects.
+//!
+//! Spacing between tokens is optional (`hp>=3`, `gold -=price`), integers may be negative, `true` and
+//! `false` stand for `1` and `0`, and `//` starts a comment that runs to the end of the line.
+//!
+//! Fact names are identifiers, shared with `FactMap::new_fact`:
+//! ```text
+//! name    := segment ("." segment)*
+//! segment := (letter | "_") (letter | digit | "_")*
+//! ```
+//! where letters and digits are Unicode alphabetic and alphanumeric characters, and `name` must not be a
+//! keyword (`true` or `false`).
+
+use core::ops::Range;
+
+use crate::errors::{NewFactError, ParseError, ParseErrorKind};
+
+pub(crate) const KEYWORDS: [&str; 2] = ["true", "false"];
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum TokenKind<'a> {
+	Ident(&'a str),
+	Int(i64),
+	Op(&'a str),
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) struct Token<'a> {
+	pub kind: TokenKind<'a>,
+	pub span: Range<usize>,
+}
+
+/// A parsed `lhs op rhs`; names are left unresolved for the `FactMap`.
+#[derive(Clone, Debug)]
+pub(crate) struct Statement<'a> {
+	pub lhs: (&'a str, Range<usize>),
+	pub op: (&'a str, Range<usize>),
+	pub rhs: Token<'a>,
+}
+
+pub(crate) fn is_ident_start(ch: char) -> bool {
+	ch.is_alphabetic() || ch == '_'
+}
+
+/// Includes the `.` segment separator; segments are checked by `check_identifier`.
+pub(crate) fn is_ident_continue(ch: char) -> bool {
+	ch.is_alphanumeric() || ch == '_' || ch == '.'
+}
+
+/// Check `name` against the identifier grammar in the module docs.
+pub(crate) fn check_identifier(name: &str) -> Result<(), NewFactError> {
+	if name.is_empty() {
+		return Err(NewFactError::EmptyFactName);
+	}
+	if name.contains(char::is_whitespace) {
+		return Err(NewFactError::ContainsAsciiWhitespaceFactName);
+	}
+	if let Some(ch) = name.chars().find(|ch| !is_ident_continue(*ch)) {
+		return Err(NewFactError::InvalidCharacterFactName(ch));
+	}
+	for segment in name.split('.') {
+		match segment.chars().next() {
+			None => return Err(NewFactError::EmptySegmentFactName),
+			Some(ch) if ch.is_numeric() => return Err(NewFactError::InitialDigitFactName),
+			Some(ch) if !is_ident_start(ch) => {
+				return Err(NewFactError::InvalidCharacterFactName(ch));
+			}
+			Some(_) => {}
+		}
+	}
+	if KEYWORDS.contains(&name) {
+		return Err(NewFactError::ReservedFactName(name.into()));
+	}
+	Ok(())
+}
+
+fn is_op_char(ch: char) -> bool {
+	matches!(ch, '=' | '!' | '<' | '>' | '+' | '-' | ':')
+}
+
+pub(crate) fn tokenize(input: &str) -> Result<Vec<Token<'_>>, ParseError> {
+	let bytes = input.as_bytes();
+	let mut tokens = Vec::new();
+	let mut chars = input.char_indices().peekable();
+	while let Some(&(start, ch)) = chars.peek() {
+		if ch.is_whitespace() {
+			chars.next();
+		} else if input[start..].starts_with("//") {
+			while chars.next_if(|&(_, c)| c != '\n').is_some() {}
+		} else if is_ident_start(ch) {
+			let mut end = start;
+			while let Some(&(i, c)) = chars.peek() {
+				if !is_ident_continue(c) {
+					break;
+				}
+				end = i + c.len_utf8();
+				chars.next();
+			}
+			tokens.push(Token {
+				kind: TokenKind::Ident(&input[start..end]),
+				span: start..end,
+			});
+		} else if ch.is_ascii_digit()
+			|| (ch == '-' && bytes.get(start + 1).is_some_and(u8::is_ascii_digit))
+		{
+			chars.next();
+			let mut end = start + 1;
+			while let Some(&(i, c)) = chars.peek() {
+				if !c.is_ascii_alphanumeric() && c != '_' {
+					break;
+				}
+				end = i + 1;
+				chars.next();
+			}
+			let text = &input[start..end];
+			let Ok(int) = text.parse::<i64>() else {
+				return Err(ParseError::new(
+					ParseErrorKind::InvalidInteger(text.into()),
+					input,
+					start..end,
+				));
+			};
+			tokens.push(Token {
+				kind: TokenKind::Int(int),
+				span: start..end,
+			});
+		} else if is_op_char(ch) {
+			chars.next();
+			let mut end = start + 1;
+			while let Some(&(i, c)) = chars.peek() {
+				// `>-3` is `>` followed by `-3`, but `-=` and `=-` stay together.
+				let negative_literal = c == '-' && bytes.get(i + 1).is_some_and(u8::is_ascii_digit);
+				if !is_op_char(c) || negative_literal {
+					break;
+				}
+				end = i + 1;
+				chars.next();
+			}
+			tokens.push(Token {
+				kind: TokenKind::Op(&input[start..end]),
+				span: start..end,
+			});
+		} else {
+			let end = start + ch.len_utf8();
+			return Err(ParseError::new(
+				ParseErrorKind::UnexpectedCharacter(ch),
+				input,
+				start..end,
+			));
+		}
+	}
+	Ok(tokens)
+}
+
+/// Parse exactly one `lhs op rhs`, where `lhs` is a name and `rhs` is a name or integer.
+pub(crate) fn parse_statement(input: &str) -> Result<Statement<'_>, ParseError> {
+	let tokens = tokenize(input)?;
+	let mut tokens = tokens.into_iter();
+	let unexpected = |token: Option<Token>, expected: &'static str| match token {
+		Some(token) => ParseError::new(
+			ParseErrorKind::UnexpectedToken {
+				found: input[token.span.clone()].into(),
+				expected,
+			},
+			input,
+			token.span,
+		),
+		None => ParseError::new(
+			ParseErrorKind::UnexpectedEnd(expected),
+			input,
+			input.len()..input.len(),
+		),
+	};
+
+	let lhs = match tokens.next() {
+		Some(Token {
+			kind: TokenKind::Ident(name),
+			span,
+		}) => (name, span),
+		token => return Err(unexpected(token, "a fact name")),
+	};
+	let op = match tokens.next() {
+		Some(Token {
+			kind: TokenKind::Op(op),
+			span,
+		}) => (op, span),
+		token => return Err(unexpected(token, "an operator")),
+	};
+	let rhs = match tokens.next() {
+		Some(
+			token @ Token {
+				kind: TokenKind::Ident(_) | TokenKind::Int(_),
+				..
+			},
+		) => token,
+		token => return Err(unexpected(token, "a fact name or integer")),
+	};
+	if let Some(token) = tokens.next() {
+		return Err(unexpected(Some(token), "the end of input"));
+	}
+	Ok(Statement { lhs, op, rhs })
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	fn kinds(input: &str) -> Vec<TokenKind<'_>> {
+		tokenize(input)
+			.unwrap()
+			.into_iter()
+			.map(|token| token.kind)
+			.collect()
+	}
+
+	#[test]
+	fn tokenizes_without_spacing() {
+		assert_eq!(
+			kinds("hp>=3"),
+			[
+				TokenKind::Ident("hp"),
+				TokenKind::Op(">="),
+				TokenKind::Int(3)
+			]
+		);
+		assert_eq!(
+			kinds("gold-=-2 // pay up"),
+			[
+				TokenKind::Ident("gold"),
+				TokenKind::Op("-="),
+				TokenKind::Int(-2)
+			]
+		);
+		assert_eq!(
+			kinds("gold // pay\n-= 2"),
+			[
+				TokenKind::Ident("gold"),
+				TokenKind::Op("-="),
+				TokenKind::Int(2)
+			]
+		);
+		assert_eq!(
+			kinds("x>-3"),
+			[
+				TokenKind::Ident("x"),
+				TokenKind::Op(">"),
+				TokenKind::Int(-3)
+			]
+		);
+	}
+
+	#[test]
+	fn tokenizes_unicode_and_namespaced_names() {
+		assert_eq!(
+			kinds("inventory.wood>=größe"),
+			[
+				TokenKind::Ident("inventory.wood"),
+				TokenKind::Op(">="),
+				TokenKind::Ident("größe")
+			]
+		);
+	}
+
+	#[test]
+	fn rejects_bad_input() {
+		let err = parse_statement("hp >= 3 4").unwrap_err();
+		assert_eq!(err.span, 8..9);
+		let err = parse_statement("hp >=").unwrap_err();
+		assert_eq!(
+			err.kind,
+			ParseErrorKind::UnexpectedEnd("a fact name or integer")
+		);
+		let err = parse_statement("hp == 99999999999999999999").unwrap_err();
+		assert!(matches!(err.kind, ParseErrorKind::InvalidInteger(_)));
+		let err = parse_statement("hp == 3;").unwrap_err();
+		assert_eq!(err.kind, ParseErrorKind::UnexpectedCharacter(';'));
+	}
+}
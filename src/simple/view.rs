@@ -0,0 +1,192 @@
+use core::fmt::{self, Debug, Display, Formatter};
+
+use crate::errors::FactAccessError;
+use crate::simple::FactMap;
+use crate::{FactId, Value, WorldState};
+
+/// Read access to a `WorldState` by fact name, returned by `FactMap::view`.
+/// Formats as `name = value` pairs for logging.
+#[derive(Clone, Copy)]
+pub struct FactView<'a> {
+	facts: &'a FactMap,
+	state: &'a WorldState,
+}
+
+/// Read and write access to a `WorldState` by fact name, returned by `FactMap::view_mut`.
+pub struct FactViewMut<'a> {
+	facts: &'a FactMap,
+	state: &'a mut WorldState,
+}
+
+impl FactMap {
+	#[must_use]
+	pub fn view<'a>(&'a self, state: &'a WorldState) -> FactView<'a> {
+		FactView { facts: self, state }
+	}
+
+	#[must_use]
+	pub fn view_mut<'a>(&'a self, state: &'a mut WorldState) -> FactViewMut<'a> {
+		FactViewMut { facts: self, state }
+	}
+
+	fn lookup(&self, name: &str) -> Result<FactId, FactAccessError> {
+		self.get_fact_id(name)
+			.ok_or_else(|| FactAccessError::UnknownFact {
+				name: name.into(),
+				suggestion: self.suggest(name).map(Into::into),
+			})
+	}
+}
+
+fn try_get(facts: &FactMap, state: &WorldState, name: &str) -> Result<Value, FactAccessError> {
+	let fact = facts.lookup(name)?;
+	state
+		.try_get(fact)
+		.map_err(|_| FactAccessError::OutOfRange {
+			name: name.into(),
+			fact,
+			len: state.len(),
+		})
+}
+
+impl<'a> FactView<'a> {
+	/// # Panics
+	/// If `name` is not a fact, or the state is too short to hold it; see `try_get`.
+	#[must_use]
+	pub fn get(&self, name: &str) -> Value {
+		self.try_get(name).unwrap_or_else(|err| panic!("{err}"))
+	}
+
+	/// # Errors
+	/// - `FactAccessError::UnknownFact`: if `name` is not a fact in the `FactMap`
+	/// - `FactAccessError::OutOfRange`: if the state is too short to hold the fact
+	pub fn try_get(&self, name: &str) -> Result<Value, FactAccessError> {
+		try_get(self.facts, self.state, name)
+	}
+
+	/// `(name, value)` for every fact the state holds.
+	pub fn iter(&self) -> impl Iterator<Item = (&'a str, Value)> + 'a {
+		let state = self.state;
+		self.facts
+			.get_fact_names()
+			.iter()
+			.enumerate()
+			.take(state.len())
+			.map(move |(index, name)| {
+				let fact = FactId(u16::try_from(index).unwrap_or(u16::MAX));
+				(name.as_str(), state.get(fact))
+			})
+	}
+}
+
+impl FactViewMut<'_> {
+	/// # Panics
+	/// If `name` is not a fact, or the state is too short to hold it; see `try_get`.
+	#[must_use]
+	pub fn get(&self, name: &str) -> Value {
+		self.as_view().get(name)
+	}
+
+	/// # Errors
+	/// See `FactView::try_get`.
+	pub fn try_get(&self, name: &str) -> Result<Value, FactAccessError> {
+		try_get(self.facts, self.state, name)
+	}
+
+	/// Set a fact by name, growing the state if it is too short (like `WorldState::push_fact`).
+	///
+	/// # Panics
+	/// If `name` is not a fact; see `try_set`.
+	pub fn set(&mut self, name: &str, value: impl Into<Value>) -> &mut Self {
+		self.try_set(name, value)
+			.unwrap_or_else(|err| panic!("{err}"))
+	}
+
+	/// # Errors
+	/// - `FactAccessError::UnknownFact`: if `name` is not a fact in the `FactMap`
+	pub fn try_set(
+		&mut self,
+		name: &str,
+		value: impl Into<Value>,
+	) -> Result<&mut Self, FactAccessError> {
+		let fact = self.facts.lookup(name)?;
+		self.state.push_fact(fact, value.into());
+		Ok(self)
+	}
+
+	#[must_use]
+	pub fn as_view(&self) -> FactView<'_> {
+		FactView {
+			facts: self.facts,
+			state: self.state,
+		}
+	}
+}
+
+impl Debug for FactView<'_> {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		f.debug_map()
+			.entries(
+				self.iter()
+					.map(|(name, value)| (name, self.facts.display_value(value).to_string())),
+			)
+			.finish()
+	}
+}
+
+/// One `name = value` line per fact.
+impl Display for FactView<'_> {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		for (index, (name, value)) in self.iter().enumerate() {
+			if index > 0 {
+				writeln!(f)?;
+			}
+			write!(f, "{name} = {}", self.facts.display_value(value))?;
+		}
+		Ok(())
+	}
+}
+
+impl Debug for FactViewMut<'_> {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		Debug::fmt(&self.as_view(), f)
+	}
+}
+
+impl Display for FactViewMut<'_> {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		Display::fmt(&self.as_view(), f)
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::*;
+
+	#[test]
+	fn view_by_name() {
+		let mut facts = FactMap::new();
+		facts.new_fact("hunger").unwrap();
+		facts.new_fact("satiety").unwrap();
+		let mut state = facts.new_world_state();
+
+		facts
+			.view_mut(&mut state)
+			.set("hunger", 5)
+			.set("satiety", true);
+		let view = facts.view(&state);
+		assert!(view.get("hunger").eq(&Value::Int(5), &state));
+		assert_eq!(view.to_string(), "hunger = 5\nsatiety = 1");
+		assert_eq!(format!("{view:?}"), r#"{"hunger": "5", "satiety": "1"}"#);
+
+		assert!(matches!(
+			view.try_get("hungry"),
+			Err(FactAccessError::UnknownFact { suggestion: Some(s), .. }) if s == "hunger"
+		));
+		let short = WorldState::new(1);
+		assert!(matches!(
+			facts.view(&short).try_get("satiety"),
+			Err(FactAccessError::OutOfRange { len: 1, .. })
+		));
+	}
+}
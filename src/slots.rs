@@ -0,0 +1,81 @@
+use std::sync::Arc;
+
+/// The packed storage behind a `WorldState`: one `i32` per fact. Small states keep their slots
+/// inline, so cloning one is a plain copy; larger ones share a buffer that is only copied when a
+/// clone is first written to.
+#[derive(Clone, Debug)]
+pub(crate) enum Slots {
+	Inline { len: u8, ints: [i32; Slots::INLINE] },
+	Shared(Arc<[i32]>),
+}
+
+impl Slots {
+	/// As many slots as fit inline in 128 bytes.
+	pub(crate) const INLINE: usize = 31;
+
+	pub(crate) fn new(len: usize, fill: i32) -> Self {
+		match u8::try_from(len) {
+			Ok(short) if len <= Self::INLINE => Slots::Inline {
+				len: short,
+				ints: [fill; Self::INLINE],
+			},
+			_ => Slots::Shared(vec![fill; len].into()),
+		}
+	}
+
+	#[inline]
+	pub(crate) fn as_slice(&self) -> &[i32] {
+		match self {
+			Slots::Inline { len, ints } => &ints[..usize::from(*len)],
+			Slots::Shared(ints) => ints,
+		}
+	}
+
+	#[inline]
+	pub(crate) fn len(&self) -> usize {
+		self.as_slice().len()
+	}
+
+	#[inline]
+	pub(crate) fn get(&self, index: usize) -> Option<i32> {
+		self.as_slice().get(index).copied()
+	}
+
+	/// Copies a shared buffer first, unless this is its only user.
+	#[inline]
+	pub(crate) fn get_mut(&mut self, index: usize) -> Option<&mut i32> {
+		match self {
+			Slots::Inline { len, ints } => ints[..usize::from(*len)].get_mut(index),
+			Slots::Shared(ints) if index < ints.len() => Arc::make_mut(ints).get_mut(index),
+			Slots::Shared(_) => None,
+		}
+	}
+
+	pub(crate) fn resize(&mut self, new_len: usize, fill: i32) {
+		if let Slots::Inline { len, ints } = self
+			&& let Ok(short) = u8::try_from(new_len)
+			&& new_len <= Self::INLINE
+		{
+			if let Some(added) = ints.get_mut(usize::from(*len)..new_len) {
+				added.fill(fill);
+			}
+			*len = short;
+			return;
+		}
+		let mut resized = self.as_slice().to_vec();
+		resized.resize(new_len, fill);
+		*self = Slots::Shared(resized.into());
+	}
+}
+
+impl PartialEq for Slots {
+	fn eq(&self, other: &Self) -> bool {
+		match (self, other) {
+			// clones that were never written to
+			(Slots::Shared(ints), Slots::Shared(other_ints)) if Arc::ptr_eq(ints, other_ints) => {
+				true
+			}
+			_ => self.as_slice() == other.as_slice(),
+		}
+	}
+}
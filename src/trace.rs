@@ -0,0 +1,158 @@
+use std::collections::HashMap;
+
+use crate::action::ActionId;
+use crate::condition::Condition;
+use crate::cost::Cost;
+use crate::world::WorldState;
+
+/// Receives events from `Planner::plan_traced`, e.g. to visualize the search tree in a debugger.
+/// Every method does nothing by default, so implementors only override what they need.
+#[allow(unused_variables)]
+pub trait PlanTracer {
+	/// `state` is about to be expanded; `g_cost` is the cheapest known cost of reaching it from the start.
+	fn expand(&mut self, state: &WorldState, g_cost: Cost) {}
+
+	/// `action` can be applied to `state` and leads to `next`, which costs `g_cost` to reach this way.
+	fn apply(&mut self, state: &WorldState, action: ActionId, next: &WorldState, g_cost: Cost) {}
+
+	/// `action` was not applied to `state` because of the `failed` preconditions.
+	fn block(&mut self, state: &WorldState, action: ActionId, failed: &[Condition]) {}
+
+	/// `action`'s preconditions are met in `state`, but its effects wouldn't change anything.
+	fn skip(&mut self, state: &WorldState, action: ActionId) {}
+
+	/// `state` satisfies the goal and ends the search.
+	fn reach_goal(&mut self, state: &WorldState, g_cost: Cost) {}
+}
+
+/// A `PlanTracer` that emits every event as a `tracing` event at `TRACE` level, under the `dogsoap`
+/// target.
+#[cfg(feature = "tracing")]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TracingTracer;
+
+#[cfg(feature = "tracing")]
+impl PlanTracer for TracingTracer {
+	fn expand(&mut self, state: &WorldState, g_cost: Cost) {
+		tracing::trace!(target: "dogsoap", ?state, g_cost = g_cost.0, "expand");
+	}
+
+	fn apply(&mut self, state: &WorldState, action: ActionId, next: &WorldState, g_cost: Cost) {
+		tracing::trace!(target: "dogsoap", ?state, action = action.0, ?next, g_cost = g_cost.0, "apply");
+	}
+
+	fn block(&mut self, state: &WorldState, action: ActionId, failed: &[Condition]) {
+		tracing::trace!(target: "dogsoap", ?state, action = action.0, ?failed, "block");
+	}
+
+	fn skip(&mut self, state: &WorldState, action: ActionId) {
+		tracing::trace!(target: "dogsoap", ?state, action = action.0, "skip");
+	}
+
+	fn reach_goal(&mut self, state: &WorldState, g_cost: Cost) {
+		tracing::trace!(target: "dogsoap", ?state, g_cost = g_cost.0, "reach goal");
+	}
+}
+
+/// A tracer plus the cheapest known cost of every state generated so far, which the search itself
+/// doesn't expose.
+pub(crate) struct Trace<'a> {
+	pub(crate) tracer: &'a mut dyn PlanTracer,
+	g_costs: HashMap<WorldState, Cost>,
+}
+
+impl<'a> Trace<'a> {
+	pub(crate) fn new(tracer: &'a mut dyn PlanTracer, start: &WorldState) -> Self {
+		Self {
+			tracer,
+			g_costs: HashMap::from([(start.clone(), Cost(0))]),
+		}
+	}
+
+	pub(crate) fn g_cost(&self, state: &WorldState) -> Cost {
+		self.g_costs.get(state).copied().unwrap_or(Cost(0))
+	}
+
+	/// Remember that `state` can be reached for `g_cost`, if that's cheaper than known so far.
+	pub(crate) fn record(&mut self, state: &WorldState, g_cost: Cost) {
+		match self.g_costs.get_mut(state) {
+			Some(known) => *known = (*known).min(g_cost),
+			None => {
+				self.g_costs.insert(state.clone(), g_cost);
+			}
+		}
+	}
+}
+
+/// A `PlanTracer` that records the states and transitions explored by one search, e.g. to export with
+/// `FactMap::search_tree_dot`. A state reached along several paths is recorded once, with an edge for
+/// each path.
+#[derive(Clone, Debug, Default)]
+pub struct SearchTree {
+	/// Node 0 is the start state.
+	pub nodes: Vec<SearchNode>,
+	pub edges: Vec<SearchEdge>,
+	/// The node that satisfied the goal, if the search succeeded.
+	pub goal: Option<usize>,
+	index: HashMap<WorldState, usize>,
+}
+
+#[derive(Clone, Debug)]
+pub struct SearchNode {
+	pub state: WorldState,
+	/// The cheapest known cost of reaching this state.
+	pub g_cost: Cost,
+	/// Whether the search generated this state's successors.
+	pub expanded: bool,
+}
+
+/// `action` leads from node `from` to node `to`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct SearchEdge {
+	pub from: usize,
+	pub to: usize,
+	pub action: ActionId,
+}
+
+impl SearchTree {
+	#[must_use]
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	fn node(&mut self, state: &WorldState, g_cost: Cost) -> usize {
+		if let Some(&index) = self.index.get(state) {
+			let node = &mut self.nodes[index];
+			node.g_cost = node.g_cost.min(g_cost);
+			return index;
+		}
+		self.index.insert(state.clone(), self.nodes.len());
+		self.nodes.push(SearchNode {
+			state: state.clone(),
+			g_cost,
+			expanded: false,
+		});
+		self.nodes.len() - 1
+	}
+}
+
+impl PlanTracer for SearchTree {
+	fn expand(&mut self, state: &WorldState, g_cost: Cost) {
+		let index = self.node(state, g_cost);
+		self.nodes[index].expanded = true;
+	}
+
+	fn apply(&mut self, state: &WorldState, action: ActionId, next: &WorldState, g_cost: Cost) {
+		// `state` was recorded by `expand` just before
+		let from = match self.index.get(state) {
+			Some(&index) => index,
+			None => self.node(state, Cost(0)),
+		};
+		let to = self.node(next, g_cost);
+		self.edges.push(SearchEdge { from, to, action });
+	}
+
+	fn reach_goal(&mut self, state: &WorldState, g_cost: Cost) {
+		self.goal = Some(self.node(state, g_cost));
+	}
+}
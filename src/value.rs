@@ -1,6 +1,14 @@
-use crate::fact::FactId;
+use core::cmp::Ordering;
+
+use crate::fact::{FactId, FactRemap};
 use crate::world::WorldState;
 
+/// Comparisons involving `Value::Error` (directly, or through a `Value::Ref` that resolves to it, such as
+/// a ref cycle or a fact outside the state) have no answer: `compare` returns `None`, and every one of
+/// `eq`, `ne`, `lt`, `gt`, `le` and `ge` returns `false`, whichever side the error is on. In particular
+/// `x <= y` is not the negation of `x > y` when either is an error. `Condition::evaluate` keeps the
+/// unknown result; see `Planner::set_error_policy` to make it a planning error. This is only about
+/// conditions: `eq_even_error`, and so `WorldState` equality, count an error as equal to itself.
 #[derive(Clone, Copy, Debug, Hash)]
 #[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Value {
@@ -13,6 +21,23 @@ impl Value {
 	pub const FALSE: Value = Value::Int(0);
 	pub const TRUE: Value = Value::Int(1);
 
+	#[must_use]
+	pub fn remapped(&self, remap: &FactRemap) -> Value {
+		match *self {
+			Value::Ref(fact) => Value::Ref(remap.get(fact)),
+			value => value,
+		}
+	}
+
+	/// The fact this value refers to, if it is a `Value::Ref`.
+	#[must_use]
+	pub fn ref_fact(&self) -> Option<FactId> {
+		match self {
+			Value::Ref(fact) => Some(*fact),
+			_ => None,
+		}
+	}
+
 	#[must_use]
 	pub fn is_ref(&self) -> bool {
 		matches!(self, Value::Ref(_))
@@ -23,92 +48,101 @@ impl Value {
 		matches!(self, Value::Error)
 	}
 
+	/// A `Value::Ref` to a fact outside of `state` resolves to `Value::Error`.
 	#[must_use]
 	pub fn resolve(&self, state: &WorldState) -> Value {
 		match self {
-			Value::Ref(f) => state.get(*f),
+			Value::Ref(f) => state.try_get(*f).unwrap_or(Value::Error),
 			_ => *self,
 		}
 	}
 
+	/// Follow `Value::Ref`s until a non-ref value is reached.
+	/// A chain that is longer than `state` has facts must contain a cycle, and resolves to `Value::Error`.
 	#[must_use]
 	pub fn resolve_fully(&self, state: &WorldState) -> Value {
 		let mut val = *self;
-		while let Value::Ref(f) = val {
-			val = state.get(f);
+		for _ in 0..=state.len() {
+			match val {
+				Value::Ref(f) => val = state.try_get(f).unwrap_or(Value::Error),
+				_ => return val,
+			}
 		}
-		val
+		Value::Error
 	}
 
+	/// Whether both values resolve to the same thing, counting `Value::Error` as equal to itself.
+	/// Unlike `eq`, this is about identity rather than a condition: a state holding an error is equal
+	/// to itself.
 	#[must_use]
 	pub fn eq_even_error(&self, other: &Self, state: &WorldState) -> bool {
+		self.resolve_fully(state)
+			.same_resolved(other.resolve_fully(state))
+	}
+
+	/// `eq_even_error` for values that are already resolved, possibly in different states.
+	#[must_use]
+	pub(crate) fn same_resolved(self, other: Value) -> bool {
+		match (self, other) {
+			(Value::Int(lhs), Value::Int(rhs)) => lhs == rhs,
+			(Value::Error, Value::Error) => true,
+			_ => false,
+		}
+	}
+
+	/// Whether both values are the same without resolving them: a `Value::Ref` is only the same as a
+	/// ref to the same fact.
+	#[must_use]
+	pub(crate) fn same_stored(self, other: Value) -> bool {
+		match (self, other) {
+			(Value::Ref(lhs), Value::Ref(rhs)) => lhs == rhs,
+			_ => self.same_resolved(other),
+		}
+	}
+
+	/// Compare both values after resolving them, or `None` if either is (or resolves to) `Value::Error`.
+	#[must_use]
+	pub fn compare(&self, other: &Self, state: &WorldState) -> Option<Ordering> {
 		if let Value::Int(lhs) = self.resolve_fully(state)
 			&& let Value::Int(rhs) = other.resolve_fully(state)
 		{
-			lhs == rhs
+			Some(lhs.cmp(&rhs))
 		} else {
-			false
+			None
 		}
 	}
 
 	#[must_use]
 	pub fn eq(&self, other: &Self, state: &WorldState) -> bool {
-		if self.is_error() || other.is_error() {
-			return false;
-		}
-		self.eq_even_error(other, state)
+		self.compare(other, state) == Some(Ordering::Equal)
 	}
 
 	#[must_use]
 	pub fn ne(&self, other: &Self, state: &WorldState) -> bool {
-		if self.is_error() || other.is_error() {
-			return false;
-		}
-		!self.eq(other, state)
+		self.compare(other, state)
+			.is_some_and(|ordering| ordering != Ordering::Equal)
 	}
 
 	#[must_use]
 	pub fn lt(&self, other: &Self, state: &WorldState) -> bool {
-		if self.is_error() || other.is_error() {
-			return false;
-		}
-		if let Value::Int(lhs) = self.resolve_fully(state)
-			&& let Value::Int(rhs) = other.resolve_fully(state)
-		{
-			lhs < rhs
-		} else {
-			false
-		}
+		self.compare(other, state) == Some(Ordering::Less)
 	}
 
 	#[must_use]
 	pub fn gt(&self, other: &Self, state: &WorldState) -> bool {
-		if self.is_error() || other.is_error() {
-			return false;
-		}
-		if let Value::Int(lhs) = self.resolve_fully(state)
-			&& let Value::Int(rhs) = other.resolve_fully(state)
-		{
-			lhs > rhs
-		} else {
-			false
-		}
+		self.compare(other, state) == Some(Ordering::Greater)
 	}
 
 	#[must_use]
 	pub fn le(&self, other: &Self, state: &WorldState) -> bool {
-		if self.is_error() || other.is_error() {
-			return false;
-		}
-		!self.gt(other, state)
+		self.compare(other, state)
+			.is_some_and(|ordering| ordering != Ordering::Greater)
 	}
 
 	#[must_use]
 	pub fn ge(&self, other: &Self, state: &WorldState) -> bool {
-		if self.is_error() || other.is_error() {
-			return false;
-		}
-		!self.lt(other, state)
+		self.compare(other, state)
+			.is_some_and(|ordering| ordering != Ordering::Less)
 	}
 
 	#[must_use]
@@ -142,6 +176,30 @@ impl Value {
 	}
 }
 
+impl From<i64> for Value {
+	fn from(value: i64) -> Self {
+		Value::Int(value)
+	}
+}
+
+impl From<i32> for Value {
+	fn from(value: i32) -> Self {
+		Value::Int(i64::from(value))
+	}
+}
+
+impl From<bool> for Value {
+	fn from(value: bool) -> Self {
+		Value::Int(i64::from(value))
+	}
+}
+
+impl From<FactId> for Value {
+	fn from(value: FactId) -> Self {
+		Value::Ref(value)
+	}
+}
+
 impl Default for Value {
 	fn default() -> Self {
 		Value::Int(0)
@@ -1,24 +1,85 @@
+use std::sync::Arc;
+
 use crate::effect::Effect;
-use crate::fact::FactId;
+use crate::errors::StateError;
+use crate::fact::{FactId, FactRange, FactRemap};
+use crate::slots::Slots;
 use crate::value::Value;
 
 /// Regarding `Eq`, `PartialEq`, and `Hash`:
-/// `Value::Error` is considered equal to `Value::Error` here so that comparing hashes produces the same result as comparing values.
+/// facts are compared by their resolved values, and `Value::Error` (including a ref cycle) is
+/// considered equal to `Value::Error` here, so every state is equal to itself and comparing hashes
+/// produces the same result as comparing values.
+///
+/// Facts are packed into one `i32` slot each, stored inline for small states, so the planner can
+/// clone a state for every successor without allocating. Larger states share their slots between
+/// clones until one of them is written to. Values that don't fit a slot (refs, errors, and ints
+/// outside of `i32`) are kept to the side, so they cost a little more to read.
+///
+/// A state made with `layered` reads through to a shared base state (time of day, weather, alarms)
+/// and only stores the facts set on top of it, so it is cheap to clone. It compares, hashes and
+/// serializes the same as a flat state with the same values.
 #[derive(Clone, Debug)] // Eq, Hash are required by astar
-#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(
+	feature = "serde",
+	derive(serde::Serialize, serde::Deserialize),
+	serde(from = "FlatState", into = "FlatState")
+)]
 pub struct WorldState {
-	pub(crate) values: Vec<Value>, // indexed by FactId
+	slots: Slots, // indexed by FactId: each fact's int, or `OTHER` to look in `extra`; empty if layered
+	extra: Option<Box<Extra>>, // boxed to keep plain states, which the planner stores many of, small
+}
+
+/// A slot whose value is in `Extra::others`.
+const OTHER: i32 = i32::MIN;
+
+#[derive(Clone, Debug, Default)]
+struct Extra {
+	/// Sorted by `FactId`: the values that don't fit a slot, or every value set on top of the base
+	/// of a layered state.
+	others: Vec<(FactId, Value)>,
+	layer: Option<Layer>,
+}
+
+#[derive(Clone, Debug)]
+struct Layer {
+	base: Arc<WorldState>,
+	len: usize,
+}
+
+/// How every `WorldState` is serialized, layered or not.
+#[cfg(feature = "serde")]
+#[derive(serde::Serialize, serde::Deserialize)]
+struct FlatState {
+	values: Vec<Value>,
+}
+
+#[cfg(feature = "serde")]
+impl From<FlatState> for WorldState {
+	fn from(flat: FlatState) -> Self {
+		let mut state = Self::new(flat.values.len());
+		for (index, value) in flat.values.into_iter().enumerate() {
+			state.set(FactId(u16::try_from(index).unwrap_or(u16::MAX)), value);
+		}
+		state
+	}
+}
+
+#[cfg(feature = "serde")]
+impl From<WorldState> for FlatState {
+	fn from(state: WorldState) -> Self {
+		Self {
+			values: state.values().collect(),
+		}
+	}
 }
 
 impl PartialEq for WorldState {
 	fn eq(&self, other: &Self) -> bool {
-		if self.values.len() != other.values.len() {
-			return false;
+		if self.extra.is_some() || other.extra.is_some() {
+			return self.eq_extra(other);
 		}
-		self.values
-			.iter()
-			.zip(&other.values)
-			.all(|p| p.0.eq_even_error(p.1, self))
+		self.slots == other.slots
 	}
 }
 
@@ -26,11 +87,23 @@ impl Eq for WorldState {}
 
 impl core::hash::Hash for WorldState {
 	fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
-		self.values
-			.iter()
-			.map(|v| v.resolve_fully(self))
-			.collect::<Vec<Value>>()
-			.hash(state);
+		// The resolved values as ints, with errors as `i64::MIN`, without collecting them first.
+		if self.extra.is_some() {
+			return self.hash_extra(state);
+		}
+		let slots = self.slots.as_slice();
+		state.write_usize(slots.len());
+		for int in slots {
+			state.write_i64(i64::from(*int));
+		}
+	}
+}
+
+/// The slot for `value`, if it fits in one.
+fn pack(value: Value) -> Option<i32> {
+	match value {
+		Value::Int(int) => i32::try_from(int).ok().filter(|int| *int != OTHER),
+		_ => None,
 	}
 }
 
@@ -38,16 +111,90 @@ impl WorldState {
 	#[must_use]
 	pub fn new(num_facts: usize) -> Self {
 		Self {
-			values: vec![Value::default(); num_facts],
+			slots: Slots::new(num_facts, 0),
+			extra: None,
 		}
 	}
 
+	/// A state that reads every fact from `base` until it is set here. Facts beyond `base.len()`
+	/// can be added with `push_fact`, e.g. an agent's own facts on top of the shared ones.
+	#[must_use]
+	pub fn layered(base: Arc<WorldState>) -> Self {
+		Self {
+			slots: Slots::new(0, 0),
+			extra: Some(Box::new(Extra {
+				others: Vec::new(),
+				layer: Some(Layer {
+					len: base.len(),
+					base,
+				}),
+			})),
+		}
+	}
+
+	fn layer(&self) -> Option<&Layer> {
+		self.extra.as_ref()?.layer.as_ref()
+	}
+
+	/// The shared state this one reads through to, if it was made with `layered`.
+	#[must_use]
+	pub fn base(&self) -> Option<&Arc<WorldState>> {
+		self.layer().map(|layer| &layer.base)
+	}
+
+	/// The facts set on top of `base`, in `FactId` order; empty for a flat state.
+	#[must_use]
+	pub fn overlay(&self) -> &[(FactId, Value)] {
+		match &self.extra {
+			Some(extra) if extra.layer.is_some() => &extra.others,
+			_ => &[],
+		}
+	}
+
+	/// Read through to `new_base` instead, keeping the facts set on top of the old base, e.g. when the
+	/// shared state is updated. Does nothing to a flat state.
+	pub fn rebase(&mut self, new_base: Arc<WorldState>) -> &mut Self {
+		if let Some(extra) = &mut self.extra
+			&& let Some(layer) = &mut extra.layer
+		{
+			let top = extra
+				.others
+				.last()
+				.map_or(0, |(fact, _)| usize::from(fact.0) + 1);
+			layer.len = new_base.len().max(top);
+			layer.base = new_base;
+		}
+		self
+	}
+
+	/// A copy that stores every fact itself, with no base.
+	#[must_use]
+	pub fn flattened(&self) -> Self {
+		let mut flat = Self::new(self.len());
+		for (index, value) in self.values().enumerate() {
+			flat.set(FactId(u16::try_from(index).unwrap_or(u16::MAX)), value);
+		}
+		flat
+	}
+
+	/// Every fact's stored value, in `FactId` order.
+	pub fn values(&self) -> impl Iterator<Item = Value> + '_ {
+		(0..self.len()).map(|index| self.get(FactId(u16::try_from(index).unwrap_or(u16::MAX))))
+	}
+
 	pub fn push_fact(&mut self, fact: FactId, value: Value) -> &mut Self {
-		let fact = usize::from(fact.0);
-		if fact >= self.values.len() {
-			self.values.resize(fact + 1, Value::default());
+		let index = usize::from(fact.0);
+		if let Some(extra) = &mut self.extra
+			&& let Some(layer) = &mut extra.layer
+		{
+			layer.len = layer.len.max(index + 1);
+			overlay_set(&mut extra.others, fact, value);
+		} else {
+			if index >= self.slots.len() {
+				self.slots.resize(index + 1, 0);
+			}
+			self.set(fact, value);
 		}
-		self.values[fact] = value;
 		self
 	}
 
@@ -58,6 +205,43 @@ impl WorldState {
 		self
 	}
 
+	/// Apply each effect in order, clamping its fact to its entry in `ranges` (indexed by `FactId`) right
+	/// after it, so a later effect on the same fact reads the clamped value. Facts without an entry are
+	/// left unbounded.
+	pub fn apply_effects_clamped(&mut self, effects: &[Effect], ranges: &[FactRange]) -> &mut Self {
+		for effect in effects {
+			effect.apply(self);
+			let fact = effect.fact();
+			if let Some(range) = ranges.get(usize::from(fact.0))
+				&& let Value::Int(int) = self.get(fact)
+			{
+				self.set(fact, Value::Int(range.clamp(int)));
+			}
+		}
+		self
+	}
+
+	/// Whether `apply_effects_clamped(effects, ranges)` would produce a state different from this one,
+	/// checked without cloning. Conservative: only reports `false` if every effect stores the value its
+	/// fact already holds, so the effects in order leave the state as it is. A ref that resolves to
+	/// the current value still counts as a change, as it may form a ref cycle with a later effect.
+	///
+	/// # Panics
+	/// If an effect's fact is out of range.
+	#[must_use]
+	pub fn is_changed_by(&self, effects: &[Effect], ranges: &[FactRange]) -> bool {
+		effects.iter().any(|effect| {
+			let index = usize::from(effect.fact().0);
+			let mut next = effect.evaluate(self);
+			if let Some(range) = ranges.get(index)
+				&& let Value::Int(int) = next
+			{
+				next = Value::Int(range.clamp(int));
+			}
+			!self.get(effect.fact()).same_stored(next)
+		})
+	}
+
 	#[must_use]
 	pub fn with_fact(&self, fact: FactId, value: Value) -> Self {
 		let mut next = self.clone();
@@ -72,15 +256,232 @@ impl WorldState {
 		next
 	}
 
+	/// Copy every fact of `other` into this state at its remapped id, e.g. to combine an agent's own
+	/// state with a squad's after merging their `FactMap`s.
+	pub fn merge_from(&mut self, other: &WorldState, remap: &FactRemap) -> &mut Self {
+		for (index, value) in other.values().enumerate() {
+			let fact = FactId(u16::try_from(index).unwrap_or(u16::MAX));
+			self.push_fact(remap.get(fact), value.remapped(remap));
+		}
+		self
+	}
+
+	#[must_use]
+	#[inline]
+	pub fn len(&self) -> usize {
+		match self.layer() {
+			Some(layer) => layer.len,
+			None => self.slots.len(),
+		}
+	}
+
+	#[must_use]
+	pub fn is_empty(&self) -> bool {
+		self.len() == 0
+	}
+
+	/// # Panics
+	/// If `fact` is out of range; see `try_get`.
 	#[inline]
 	#[must_use]
 	pub fn get(&self, fact: FactId) -> Value {
-		self.values[usize::from(fact.0)]
+		match self.slots.get(usize::from(fact.0)) {
+			Some(int) if int != OTHER => Value::Int(i64::from(int)),
+			_ => self.get_other(fact),
+		}
 	}
 
+	/// # Panics
+	/// If `fact` is out of range; see `try_set`, or `push_fact` to grow the state.
 	#[inline]
 	pub fn set(&mut self, fact: FactId, value: Value) {
-		self.values[usize::from(fact.0)] = value;
+		if let Some(int) = pack(value)
+			&& let Some(slot) = self.slots.get_mut(usize::from(fact.0))
+			&& *slot != OTHER
+		{
+			*slot = int;
+		} else {
+			self.set_other(fact, value);
+		}
+	}
+
+	/// # Errors
+	/// - `StateError::FactOutOfRange`: if `fact` is not below `len()`
+	#[inline]
+	pub fn try_get(&self, fact: FactId) -> Result<Value, StateError> {
+		match self.slots.get(usize::from(fact.0)) {
+			Some(int) if int != OTHER => Ok(Value::Int(i64::from(int))),
+			_ => self.try_get_other(fact),
+		}
+	}
+
+	/// # Errors
+	/// - `StateError::FactOutOfRange`: if `fact` is not below `len()`
+	#[inline]
+	pub fn try_set(&mut self, fact: FactId, value: Value) -> Result<(), StateError> {
+		if let Some(int) = pack(value)
+			&& let Some(slot) = self.slots.get_mut(usize::from(fact.0))
+			&& *slot != OTHER
+		{
+			*slot = int;
+			Ok(())
+		} else {
+			self.try_set_other(fact, value)
+		}
+	}
+
+	// Values that don't fit a slot, layered states (which have no slots), and out of range errors
+	// are handled out of line, so the accessors stay small for the plain ints the planner spends
+	// most of its time on.
+
+	#[inline(never)]
+	fn eq_extra(&self, other: &Self) -> bool {
+		self.len() == other.len()
+			&& self.values().zip(other.values()).all(|p| {
+				p.0.resolve_fully(self)
+					.same_resolved(p.1.resolve_fully(other))
+			})
+	}
+
+	#[inline(never)]
+	fn hash_extra<H: std::hash::Hasher>(&self, state: &mut H) {
+		state.write_usize(self.len());
+		for value in self.values() {
+			match value.resolve_fully(self) {
+				Value::Int(int) => state.write_i64(int),
+				_ => state.write_i64(i64::MIN),
+			}
+		}
+	}
+
+	#[inline(never)]
+	fn get_other(&self, fact: FactId) -> Value {
+		self.try_get_other(fact)
+			.unwrap_or_else(|err| panic!("{err}"))
+	}
+
+	#[inline(never)]
+	fn set_other(&mut self, fact: FactId, value: Value) {
+		if let Err(err) = self.try_set_other(fact, value) {
+			panic!("{err}");
+		}
+	}
+
+	#[inline(never)]
+	fn try_get_other(&self, fact: FactId) -> Result<Value, StateError> {
+		let len = self.len();
+		if usize::from(fact.0) >= len {
+			return Err(StateError::FactOutOfRange { fact, len });
+		}
+		let Some(extra) = self.extra.as_deref() else {
+			return Ok(Value::default());
+		};
+		match extra.others.binary_search_by_key(&fact, |(fact, _)| *fact) {
+			Ok(position) => Ok(extra.others[position].1),
+			// facts of a layered state that aren't set on top of the base, or past its end and not
+			// pushed yet
+			Err(_) => Ok(extra
+				.layer
+				.as_ref()
+				.and_then(|layer| layer.base.try_get(fact).ok())
+				.unwrap_or_default()),
+		}
+	}
+
+	#[inline(never)]
+	fn try_set_other(&mut self, fact: FactId, value: Value) -> Result<(), StateError> {
+		let len = self.len();
+		let index = usize::from(fact.0);
+		if index >= len {
+			return Err(StateError::FactOutOfRange { fact, len });
+		}
+		if self.layer().is_some() {
+			let extra = self.extra.get_or_insert_default();
+			overlay_set(&mut extra.others, fact, value);
+			return Ok(());
+		}
+		let Some(slot) = self.slots.get_mut(index) else {
+			return Err(StateError::FactOutOfRange { fact, len });
+		};
+		if let Some(int) = pack(value) {
+			*slot = int;
+			if let Some(extra) = &mut self.extra {
+				if let Ok(position) = extra.others.binary_search_by_key(&fact, |(fact, _)| *fact) {
+					extra.others.remove(position);
+				}
+				// back to plain ints, which compare and hash faster
+				if extra.others.is_empty() {
+					self.extra = None;
+				}
+			}
+		} else {
+			*slot = OTHER;
+			overlay_set(&mut self.extra.get_or_insert_default().others, fact, value);
+		}
+		Ok(())
+	}
+
+	/// The facts of the first `Value::Ref` cycle found (e.g. `a` refers to `b` which refers to `a`),
+	/// in the order they refer to each other. Facts in a cycle resolve to `Value::Error`.
+	#[must_use]
+	pub fn find_ref_cycle(&self) -> Option<Vec<FactId>> {
+		let next = |fact: FactId| self.try_get(fact).ok().and_then(|value| value.ref_fact());
+		for index in 0..self.len() {
+			// after `len` steps, a chain that hasn't ended is inside a cycle
+			let mut fact = FactId(u16::try_from(index).unwrap_or(u16::MAX));
+			let mut steps = 0;
+			while steps < self.len() {
+				match next(fact) {
+					Some(target) => fact = target,
+					None => break,
+				}
+				steps += 1;
+			}
+			if steps < self.len() {
+				continue;
+			}
+			let mut cycle = vec![fact];
+			let mut current = fact;
+			while let Some(target) = next(current)
+				&& target != fact
+			{
+				cycle.push(target);
+				current = target;
+			}
+			return Some(cycle);
+		}
+		None
+	}
+
+	/// Validate up front that no facts refer to each other in a cycle.
+	///
+	/// # Errors
+	/// - `StateError::RefCycle`: with the facts of the first cycle found (see `find_ref_cycle`)
+	pub fn check_ref_cycles(&self) -> Result<(), StateError> {
+		self.find_ref_cycle()
+			.map_or(Ok(()), |cycle| Err(StateError::RefCycle(cycle)))
+	}
+
+	/// `Ok` if `fact` fits in this state.
+	///
+	/// # Errors
+	/// - `StateError::FactOutOfRange`: if `fact` is not below `len()`
+	pub fn check_fact(&self, fact: FactId) -> Result<(), StateError> {
+		if usize::from(fact.0) < self.len() {
+			Ok(())
+		} else {
+			Err(StateError::FactOutOfRange {
+				fact,
+				len: self.len(),
+			})
+		}
+	}
+}
+
+fn overlay_set(overlay: &mut Vec<(FactId, Value)>, fact: FactId, value: Value) {
+	match overlay.binary_search_by_key(&fact, |(fact, _)| *fact) {
+		Ok(position) => overlay[position].1 = value,
+		Err(position) => overlay.insert(position, (fact, value)),
 	}
 }
 